@@ -25,17 +25,17 @@ impl Content for Protocol {}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Observer {
-    id: usize,
+    id: AgentId,
 }
 
 impl Agent for Observer {
     type C = Protocol;
 
-    fn id(&self) -> usize {
+    fn id(&self) -> AgentId {
         self.id
     }
 
-    fn set_id(&mut self, id: usize) {
+    fn set_id(&mut self, id: AgentId) {
         self.id = id
     }
 
@@ -64,7 +64,7 @@ impl Agent for Observer {
 pub struct ObserverFactory;
 
 impl AgentFactory<Observer> for ObserverFactory {
-    fn create(&self, agent_id: usize) -> Observer {
+    fn create(&self, agent_id: AgentId) -> Observer {
         Observer { id: agent_id }
     }
 }
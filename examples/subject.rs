@@ -25,18 +25,18 @@ impl Content for Protocol {}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Subject {
-    id: usize,
+    id: AgentId,
     event: u8,
 }
 
 impl Agent for Subject {
     type C = Protocol;
 
-    fn id(&self) -> usize {
+    fn id(&self) -> AgentId {
         self.id
     }
 
-    fn set_id(&mut self, id: usize) {
+    fn set_id(&mut self, id: AgentId) {
         self.id = id
     }
 
@@ -52,20 +52,10 @@ impl Agent for Subject {
         self.event = (self.event + 1) % 255;
 
         Some(vec![
-            Message::new(
-                Performative::Inform,
-                Recipient::Agent {
-                    agent_id: 0,
-                    system_id: OBSERVER_SYSTEM_ID,
-                },
-                0,
-                1,
-                None,
-                None,
-                None,
-                None,
-                Protocol::Event(self.event),
-            ),
+            Message::inform((OBSERVER_SYSTEM_ID, AgentId::new(0, 0)))
+                .priority(1)
+                .content(Protocol::Event(self.event))
+                .build(),
         ])
     }
 }
@@ -73,7 +63,7 @@ impl Agent for Subject {
 pub struct SubjectFactory;
 
 impl AgentFactory<Subject> for SubjectFactory {
-    fn create(&self, agent_id: usize) -> Subject {
+    fn create(&self, agent_id: AgentId) -> Subject {
         Subject {
             id: agent_id,
             event: 0,
@@ -1,7 +1,57 @@
+use std::fmt;
+
+use uuid::Uuid;
+
 use message::*;
+use agent_system::SystemId;
+use behaviour::Behaviours;
+use mailbox::Mailbox;
+use conversation::ConversationTable;
+use protocol::conformance::ConformanceChecker;
+use timer::Timers;
+
+pub type Generation = u32;
+
+/// Identifier of an agent in its system: the key of its slot and the generation of that slot.
+/// The generation is bumped each time an agent is removed, so an id kept after the removal
+/// can't target the agent reusing the slot.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct AgentId {
+    pub index: usize,
+    pub generation: Generation,
+}
+
+impl AgentId {
+    pub fn new(index: usize, generation: Generation) -> Self {
+        AgentId { index, generation }
+    }
+}
 
-pub type AgentId = usize;
+impl fmt::Display for AgentId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
 
+/// Failures reported to an agent through `on_error`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum AgentError {
+    /// A message sent by the agent couldn't be delivered to its recipient.
+    UndeliverableMessage { message_id: Uuid, recipient: AgentId },
+    /// The agent couldn't move to the system `to` and stays in its system.
+    MigrationFailed { to: SystemId },
+    /// A reply sent by the agent references a message it never received, it has been dropped.
+    InvalidReply { message_id: Uuid, in_reply_to: Id },
+    /// No reply to the message `message_id` arrived before its `reply_by` deadline.
+    ReplyTimeout { message_id: Uuid, reply_with: Id },
+    /// A message sent by the agent breaks the protocol of its conversation, it has been dropped.
+    ProtocolViolation { message_id: Uuid },
+}
+
+/// The system calls `on_spawn`, then `on_start` before the first `act`. An agent leaving the
+/// system gets exactly one teardown hook, depending on why it leaves: `on_stop` when it's killed
+/// or stopped by its supervision, `on_terminate` when it's reaped after reporting `is_dead`, and
+/// `on_migrate` when it moves to another system.
 pub trait Agent {
 
     type C: Content;
@@ -15,4 +65,88 @@ pub trait Agent {
     fn handle_message(&mut self, message: &Message<Self::C>);
 
     fn act(&mut self) -> Option<Vec<Message<Self::C>>>;
-}
\ No newline at end of file
+
+    /// Behaviours the system runs in place of `act`, the messages received by the agent are posted to them
+    /// once handled by `handle_message`.
+    fn behaviours(&mut self) -> Option<&mut Behaviours<Self::C>> { None }
+
+    /// Mailbox the system queues the messages received by the agent into, in place of calling `handle_message`.
+    fn mailbox(&mut self) -> Option<&mut Mailbox<Self::C>> { None }
+
+    /// Called once the agent has been spawned in the system.
+    fn on_spawn(&mut self) {}
+
+    /// Called before the first `act` of the agent in its system.
+    fn on_start(&mut self) {}
+
+    /// Called right before the system removes the agent it kills or stops.
+    fn on_stop(&mut self) {}
+
+    /// Called by the system right before removing an agent reporting `is_dead`.
+    fn on_terminate(&mut self) {}
+
+    /// Called right before the agent leaves the system `from` for the system `to`.
+    fn on_migrate(&mut self, _from: SystemId, _to: SystemId) {}
+
+    /// Called when the system fails to do something on behalf of the agent.
+    fn on_error(&mut self, _error: &AgentError) {}
+
+    /// Conversation table the system keeps up to date with the messages sent and received by the agent.
+    fn conversations(&mut self) -> Option<&mut ConversationTable> { None }
+
+    /// Called when the agent receives a `Subscribe` or a `RequestWhenever`. Returns the topic
+    /// of the accepted subscription, the system answers `Agree`, or `None` to `Refuse` it.
+    fn accept_subscription(&mut self, _request: &Message<Self::C>) -> Option<String> { None }
+
+    /// Contents to send to the subscribers of each topic, collected after `act`.
+    fn notifications(&mut self) -> Vec<(String, Self::C)> { Vec::new() }
+
+    /// Checker the system uses to validate the messages sent and received by the agent.
+    fn conformance(&mut self) -> Option<&mut ConformanceChecker> { None }
+
+    /// Timers whose scheduled messages and cancellations the system collects after `act`.
+    fn timers(&mut self) -> Option<&mut Timers<Self::C>> { None }
+}
+
+/// Agent of any type using the content `C`, to host heterogeneous agents in the same system.
+pub type BoxedAgent<C> = Box<dyn Agent<C=C> + Send>;
+
+impl <C: Content>Agent for BoxedAgent<C> {
+    type C = C;
+
+    fn id(&self) -> AgentId { (**self).id() }
+
+    fn set_id(&mut self, id: AgentId) { (**self).set_id(id) }
+
+    fn is_dead(&self) -> bool { (**self).is_dead() }
+
+    fn handle_message(&mut self, message: &Message<C>) { (**self).handle_message(message) }
+
+    fn act(&mut self) -> Option<Vec<Message<C>>> { (**self).act() }
+
+    fn behaviours(&mut self) -> Option<&mut Behaviours<C>> { (**self).behaviours() }
+
+    fn mailbox(&mut self) -> Option<&mut Mailbox<C>> { (**self).mailbox() }
+
+    fn on_spawn(&mut self) { (**self).on_spawn() }
+
+    fn on_start(&mut self) { (**self).on_start() }
+
+    fn on_stop(&mut self) { (**self).on_stop() }
+
+    fn on_terminate(&mut self) { (**self).on_terminate() }
+
+    fn on_migrate(&mut self, from: SystemId, to: SystemId) { (**self).on_migrate(from, to) }
+
+    fn on_error(&mut self, error: &AgentError) { (**self).on_error(error) }
+
+    fn conversations(&mut self) -> Option<&mut ConversationTable> { (**self).conversations() }
+
+    fn accept_subscription(&mut self, request: &Message<C>) -> Option<String> { (**self).accept_subscription(request) }
+
+    fn notifications(&mut self) -> Vec<(String, C)> { (**self).notifications() }
+
+    fn conformance(&mut self) -> Option<&mut ConformanceChecker> { (**self).conformance() }
+
+    fn timers(&mut self) -> Option<&mut Timers<C>> { (**self).timers() }
+}
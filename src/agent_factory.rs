@@ -1,7 +1,37 @@
-use agent::Agent;
+use std::marker::PhantomData;
+
+use agent::{Agent, AgentId, BoxedAgent};
 
 pub trait AgentFactory<A: Agent> {
 
-    fn create(&self, agent_id: usize) -> A;
+    fn create(&self, agent_id: AgentId) -> A;
+
+}
+
+/// Factory creating agents from typed spawn arguments, e.g. their initial position or role.
+pub trait AgentFactoryWith<A: Agent, P> {
+
+    fn create_with(&self, agent_id: AgentId, args: P) -> A;
+
+}
+
+/// Turn the factory of a concrete agent type into a factory of `BoxedAgent`.
+pub struct BoxedAgentFactory<F, A> {
+    factory: F,
+    agent: PhantomData<fn() -> A>,
+}
+
+impl <F: AgentFactory<A>, A: Agent>BoxedAgentFactory<F, A> {
+    pub fn new(factory: F) -> Self {
+        BoxedAgentFactory {
+            factory,
+            agent: PhantomData,
+        }
+    }
+}
 
-}
\ No newline at end of file
+impl <F: AgentFactory<A>, A: Agent + Send + 'static>AgentFactory<BoxedAgent<A::C>> for BoxedAgentFactory<F, A> {
+    fn create(&self, agent_id: AgentId) -> BoxedAgent<A::C> {
+        Box::new(self.factory.create(agent_id))
+    }
+}
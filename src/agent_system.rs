@@ -2,33 +2,128 @@ use slab::Slab;
 use shred::System;
 use zmq::Context as ZmqContext;
 
+use uuid::Uuid;
+use bincode;
+use serde::{Serialize, de::DeserializeOwned};
+
 use message::*;
-use agent::Agent;
-use agent_factory::AgentFactory;
+use control::{Control, ControlMessage, Packet};
+use agent::{Agent, AgentError, AgentId, Generation};
+use agent_factory::{AgentFactory, AgentFactoryWith};
+use supervision::{AgentFailure, ChildSpec, SupervisionStrategy, SupervisorId, SupervisorSpec, panic_reason};
+use supervisor::{Child, Supervisor};
 use dispatcher::Dispatcher;
+use protocol::conformance::ConformanceChecker;
 use message_collector::Collector;
+use timer::{Delay, TimerId, TimerRequest};
+use timer_wheel::{PendingTimer, TimerWheel};
 
 use std::{
+    any::Any,
+    cmp::Reverse,
+    mem,
+    collections::{HashMap, HashSet},
+    panic::{self, AssertUnwindSafe},
     sync::mpsc::{channel, Sender},
     net::SocketAddr,
-    time::{SystemTime, UNIX_EPOCH, Duration},
+    time::{SystemTime, UNIX_EPOCH, Duration, Instant},
 };
 
 pub type SystemId = u8;
 
+/// Time given to the destination to confirm the arrival of a migrating agent.
+const MIGRATION_TIMEOUT: Duration = Duration::from_secs(30);
+/// Time during which the messages sent to the old address of a migrated agent are forwarded.
+const FORWARDING_DURATION: Duration = Duration::from_secs(300);
+/// Time during which an agent can reply to a message it received, without `reply_by` deadline
+/// nor conversation followed by the agent.
+const REPLY_WINDOW: Duration = Duration::from_secs(300);
+
+/// How long an agent can reply to a message it received.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReplyWindow {
+    /// Until the `reply_by` deadline of the message, or the end of the reply window of the system.
+    Until(Timestamp),
+    /// As long as the conversation of the message stays in the table of the agent.
+    Conversation(Id),
+}
+
+/// Message waiting for a reply before its `reply_by` deadline.
+struct PendingReply {
+    agent_id: AgentId,
+    message_id: Uuid,
+    deadline: Timestamp,
+}
+
+/// Subscription accepted by an agent, notified by replying to its request.
+struct Subscription<C> {
+    topic: String,
+    request: Message<C>,
+}
+
+/// Reinstantiate an agent migrating from another system.
+type AgentDecoder<A> = fn(&[u8]) -> Option<A>;
+
+/// Agent which left the system, kept until the destination confirms its arrival.
+struct Migration<C> {
+    to: SystemId,
+    agent: Vec<u8>,
+    replies: Vec<(Id, ReplyWindow)>,
+    messages: Vec<Message<C>>,
+    /// The agent comes back if its arrival isn't confirmed by then.
+    deadline: Timestamp,
+}
+
+/// New address of an agent which has migrated, used until `until`.
+struct Forward {
+    system_id: SystemId,
+    agent_id: AgentId,
+    until: Timestamp,
+}
+
 pub struct AgentSystem<A: Agent<C=C>, C: Content> {
     id: SystemId,
     agents: Slab<A>,
+    generations: Vec<Generation>,
+    starting: Vec<AgentId>,
     outbox: Vec<Message<C>>,
-    sender: Sender<Message<C>>,
-    factory: Box<AgentFactory<A> + Send>,
+    controls: Vec<Control>,
+    remote_spawns: HashMap<Uuid, Vec<AgentId>>,
+    migrations: HashMap<AgentId, Migration<C>>,
+    forwards: HashMap<AgentId, Forward>,
+    migration_timeout: Duration,
+    forwarding_duration: Duration,
+    agent_decoder: Option<AgentDecoder<A>>,
+    sender: Sender<Packet<C>>,
+    factory: Box<dyn AgentFactory<A> + Send>,
+    factories: HashMap<String, Box<dyn AgentFactory<A> + Send>>,
+    /// Factory which spawned each agent, `None` being the default factory. Agents spawned
+    /// with arguments or coming from another system aren't there as they can't be restarted.
+    spawned_by: HashMap<AgentId, Option<String>>,
+    supervision: SupervisionStrategy,
+    failures: Option<Sender<AgentFailure>>,
+    supervisors: Slab<Supervisor<A>>,
+    supervised: HashMap<AgentId, (SupervisorId, usize)>,
+    /// `reply_with` of the messages received by each agent, which its replies can reference
+    /// during their reply window.
+    received_replies: HashMap<AgentId, HashMap<Id, ReplyWindow>>,
+    reply_window: Duration,
+    /// Messages sent with a `reply_by` deadline, by their `reply_with`.
+    pending_replies: HashMap<Id, PendingReply>,
+    subscriptions: HashMap<AgentId, Vec<Subscription<C>>>,
+    /// Messages scheduled to be sent later.
+    timers: TimerWheel<C>,
+    /// Ids of the messages fired by a timer to the agent which scheduled them, delivered despite their sender.
+    reminders: HashSet<Uuid>,
     dispatcher: Dispatcher<C>,
     collector: Collector<C>,
+    dead_letters: Option<Sender<Message<C>>>,
+    nb_stale_messages: usize,
 }
 
 impl <A: Agent<C=C>, C: Content>AgentSystem<A, C> {
 
-    pub fn new(id: SystemId, factory: Box<AgentFactory<A> + Send>, addr: SocketAddr) -> Self {
+    pub fn new(id: SystemId, factory: Box<dyn AgentFactory<A> + Send>, addr: SocketAddr) -> Self {
         trace!("Creating the system {}", id);
 
         let zmq_ctx = ZmqContext::new();
@@ -39,11 +134,34 @@ impl <A: Agent<C=C>, C: Content>AgentSystem<A, C> {
         let mut agent_system = AgentSystem {
             id,
             agents: Slab::new(),
+            generations: Vec::new(),
+            starting: Vec::new(),
             outbox: Vec::new(),
+            controls: Vec::new(),
+            remote_spawns: HashMap::new(),
+            migrations: HashMap::new(),
+            forwards: HashMap::new(),
+            migration_timeout: MIGRATION_TIMEOUT,
+            forwarding_duration: FORWARDING_DURATION,
+            agent_decoder: None,
             sender: sender.clone(),
             factory,
+            factories: HashMap::new(),
+            spawned_by: HashMap::new(),
+            supervision: SupervisionStrategy::Stop,
+            failures: None,
+            supervisors: Slab::new(),
+            supervised: HashMap::new(),
+            received_replies: HashMap::new(),
+            reply_window: REPLY_WINDOW,
+            pending_replies: HashMap::new(),
+            subscriptions: HashMap::new(),
+            timers: TimerWheel::new(),
+            reminders: HashSet::new(),
             dispatcher,
             collector,
+            dead_letters: None,
+            nb_stale_messages: 0,
         };
 
         // Register itself to dispatch the message to the same agents.
@@ -53,200 +171,3014 @@ impl <A: Agent<C=C>, C: Content>AgentSystem<A, C> {
     }
 
 
-    pub fn spawn_agent(&mut self) {
+    pub fn spawn_agent(&mut self) -> AgentId {
         trace!("Creating an agent on system {}", self.id());
-        let entry_agent = self.agents.vacant_entry();
-        let agent = self.factory.create(entry_agent.key());
-        entry_agent.insert(agent);
+        let id = self.next_agent_id();
+        let agent = self.factory.create(id);
+        self.spawned_by.insert(id, None);
+        self.insert_spawned_agent(id, agent)
     }
 
-    pub fn spawn_swarm(&mut self, count: usize) {
+    pub fn spawn_swarm(&mut self, count: usize) -> Vec<AgentId> {
         trace!("Creating {} agent on system {}", count, self.id());
-        for _ in 0..count {
-            self.spawn_agent();
+        (0..count).map(|_| self.spawn_agent()).collect()
+    }
+
+    /// Spawn an agent created by the factory from the spawn arguments.
+    pub fn spawn_agent_with<F, P>(&mut self, factory: &F, args: P) -> AgentId
+        where F: AgentFactoryWith<A, P>
+    {
+        trace!("Creating an agent with spawn arguments on system {}", self.id);
+        let id = self.next_agent_id();
+        let agent = factory.create_with(id, args);
+        self.insert_spawned_agent(id, agent)
+    }
+
+    /// Spawn one agent for each spawn arguments of the iterator.
+    pub fn spawn_swarm_with<F, P, I>(&mut self, factory: &F, args: I) -> Vec<AgentId>
+        where F: AgentFactoryWith<A, P>, I: IntoIterator<Item=P>
+    {
+        args.into_iter().map(|a| self.spawn_agent_with(factory, a)).collect()
+    }
+
+    /// Register a factory which can then be used to spawn agents by its name.
+    pub fn register_factory(&mut self, name: &str, factory: Box<dyn AgentFactory<A> + Send>) {
+        trace!("Registering the factory {} on system {}", name, self.id);
+        self.factories.insert(name.to_string(), factory);
+    }
+
+    /// Spawn an agent with the factory registered under this name, if any.
+    pub fn spawn_agent_of(&mut self, name: &str) -> Option<AgentId> {
+        let id = self.next_agent_id();
+        let agent = match self.factories.get(name) {
+            Some(factory) => factory.create(id),
+            None => {
+                warn!("No factory {} registered on system {}", name, self.id);
+                return None
+            },
+        };
+
+        self.spawned_by.insert(id, Some(name.to_string()));
+        Some(self.insert_spawned_agent(id, agent))
+    }
+
+    pub fn spawn_swarm_of(&mut self, name: &str, count: usize) -> Option<Vec<AgentId>> {
+        trace!("Creating {} agent {} on system {}", count, name, self.id);
+        (0..count).map(|_| self.spawn_agent_of(name)).collect()
+    }
+
+    /// Start a supervision tree and return the id of its root supervisor.
+    pub fn spawn_supervisor(&mut self, spec: SupervisorSpec<A>) -> SupervisorId {
+        trace!("Starting a supervision tree on system {}", self.id);
+        self.start_supervisor(None, spec)
+    }
+
+    fn start_supervisor(&mut self, parent: Option<SupervisorId>, spec: SupervisorSpec<A>) -> SupervisorId {
+        let supervisor_id = self.supervisors.insert(Supervisor::new(parent, spec.strategy, spec.intensity));
+
+        for child in spec.children {
+            let child = match child {
+                ChildSpec::Worker(factory) => Child::Worker { factory, agent: None },
+                ChildSpec::Supervisor(spec) => Child::Supervisor(self.start_supervisor(Some(supervisor_id), spec)),
+            };
+
+            self.supervisors[supervisor_id].children.push(child);
+        }
+
+        for index in 0..self.supervisors[supervisor_id].children.len() {
+            if let Child::Worker { .. } = self.supervisors[supervisor_id].children[index] {
+                self.start_child(supervisor_id, index);
+            }
         }
+
+        supervisor_id
     }
 
-    pub fn process_agent(&mut self) {
-        let occurred = SystemTime::now()
-                                .duration_since(UNIX_EPOCH)
-                                .unwrap_or(Duration::new(0,0));
+    fn start_child(&mut self, supervisor_id: SupervisorId, index: usize) {
+        let id = self.next_agent_id();
+        let agent = match self.supervisors[supervisor_id].children[index] {
+            Child::Worker { ref factory, .. } => factory.create(id),
+            Child::Supervisor(child_id) => {
+                self.supervisors[child_id].reset_restarts();
+                for child_index in 0..self.supervisors[child_id].children.len() {
+                    self.start_child(child_id, child_index);
+                }
+                return;
+            },
+        };
 
-        for (_, agent) in self.agents.iter_mut() {
-            if let Some(mut messages) = agent.act() {
-                for m in messages.iter_mut() {
-                    m.set_sender((self.id, agent.id()));
-                    m.set_occurred(occurred.as_secs());
+        if let Child::Worker { ref mut agent, .. } = self.supervisors[supervisor_id].children[index] {
+            *agent = Some(id);
+        }
+        self.supervised.insert(id, (supervisor_id, index));
+        self.insert_spawned_agent(id, agent);
+    }
+
+    fn stop_child(&mut self, supervisor_id: SupervisorId, index: usize) {
+        match self.supervisors[supervisor_id].children[index] {
+            Child::Worker { agent: Some(id), .. } => {
+                if self.is_alive(id) {
+                    self.remove_agent(id.index);
+                }
+            },
+            Child::Worker { agent: None, .. } => {},
+            Child::Supervisor(child_id) => {
+                for child_index in (0..self.supervisors[child_id].children.len()).rev() {
+                    self.stop_child(child_id, child_index);
                 }
+            },
+        }
+    }
+
+    /// Restart the children of the supervisor according to its strategy, or give up
+    /// and escalate the failure to its parent when it restarts too often.
+    fn handle_child_failure(&mut self, supervisor_id: SupervisorId, index: usize) {
+        if self.supervisors[supervisor_id].record_restart(Instant::now()) {
+            let children = self.supervisors[supervisor_id].children_to_restart(index);
+
+            for child_index in children.clone().rev() {
+                self.stop_child(supervisor_id, child_index);
+            }
 
-                self.outbox.append(&mut messages);
+            for child_index in children {
+                self.start_child(supervisor_id, child_index);
             }
+            return;
         }
-        self.outbox.sort();
-    }
 
-    pub fn send_agents_messages(&mut self) {
-        let messages = self.outbox.drain(..);
-        self.dispatcher.dispatch_messages(messages);
+        warn!("The supervisor {} of the system {} exceeded its restart intensity", supervisor_id, self.id);
+        for child_index in (0..self.supervisors[supervisor_id].children.len()).rev() {
+            self.stop_child(supervisor_id, child_index);
+        }
+
+        match self.supervisors[supervisor_id].parent {
+            Some(parent_id) => {
+                match self.supervisors[parent_id].index_of_supervisor(supervisor_id) {
+                    Some(parent_index) => self.handle_child_failure(parent_id, parent_index),
+                    None => error!("The supervisor {} isn't a child of its parent {}", supervisor_id, parent_id),
+                }
+            },
+            None => {
+                error!("The supervision tree {} of the system {} is shut down", supervisor_id, self.id);
+                self.remove_supervisor(supervisor_id);
+            },
+        }
     }
 
-    pub fn collect_messages(&mut self) {
-        self.collector.collect_messages();
+    fn remove_supervisor(&mut self, supervisor_id: SupervisorId) {
+        let supervisor = self.supervisors.remove(supervisor_id);
+
+        for child in supervisor.children {
+            if let Child::Supervisor(child_id) = child {
+                self.remove_supervisor(child_id);
+            }
+        }
     }
 
-    pub fn distribute_messages_collected_to_the_agents(&mut self) {
-        let sys_id = self.id();
+    /// Agents currently running under the supervisor and its child supervisors.
+    pub fn get_supervised_agents(&self, supervisor_id: SupervisorId) -> Vec<AgentId> {
+        let mut agents = Vec::new();
 
-        if let Some(messages) = self.collector.drain_inbox() {
-            for m in messages {
-                match m.recipient {
-                    Recipient::Agent{ system_id: _, agent_id } => {
-                        if let Some(agent) = self.agents.get_mut(agent_id) {
-                            if agent.id() != m.sender.1 || sys_id != m.sender.0 {
-                                agent.handle_message(&m);
-                            }
-                        }
-                    },
-                    Recipient::Broadcast{ system_id: _ } => {
-                        self.agents
-                            .iter_mut()
-                            .filter(|&(_,ref agent)|
-                                agent.id() != m.sender.1
-                                || sys_id != m.sender.0)
-                            .for_each(|(_, agent)| agent.handle_message(&m));
-                    }
+        if let Some(supervisor) = self.supervisors.get(supervisor_id) {
+            for child in supervisor.children.iter() {
+                match *child {
+                    Child::Worker { agent: Some(id), .. } => agents.push(id),
+                    Child::Worker { agent: None, .. } => {},
+                    Child::Supervisor(child_id) => agents.extend(self.get_supervised_agents(child_id)),
                 }
             }
         }
+
+        agents
     }
 
-    pub fn add_local_observer_system(&mut self, system_id: SystemId, channel_sender: Sender<Message<C>>) {
-        trace!("Adding the local observer system {}", system_id);
-        self.dispatcher.add_local_sender(system_id, channel_sender);
+    pub fn is_supervisor_running(&self, supervisor_id: SupervisorId) -> bool {
+        self.supervisors.contains(supervisor_id)
     }
 
-    pub fn add_remote_observer_system(&mut self, rs_id: SystemId, rs_addr: SocketAddr) {
-        trace!("Adding the remote observer system {} - {}", rs_id, rs_addr);
-        self.collector.add_remote_collector(rs_addr);
+    fn next_agent_id(&mut self) -> AgentId {
+        let index = self.agents.vacant_key();
+
+        if index >= self.generations.len() {
+            self.generations.resize(index + 1, 0);
+        }
+
+        AgentId::new(index, self.generations[index])
     }
 
-    #[inline]
-    pub fn get_sender(&self) -> Sender<Message<C>> {
-        self.sender.clone()
+    fn insert_agent(&mut self, id: AgentId, agent: A) -> AgentId {
+        let index = self.agents.insert(agent);
+        debug_assert_eq!(id.index, index);
+        self.starting.push(id);
+        id
     }
 
-    #[inline]
-    pub fn get_nb_message_inbox(&self) -> usize {
-        self.outbox.len()
+    fn insert_spawned_agent(&mut self, id: AgentId, agent: A) -> AgentId {
+        self.insert_agent(id, agent);
+        self.agents[id.index].on_spawn();
+        id
     }
 
-    #[inline]
-    pub fn id(&self) -> u8 {
-        self.id
+    /// Remove the agent with this id. Nothing is removed if the id is stale.
+    pub fn kill_agent(&mut self, id: AgentId) -> Option<A> {
+        if self.is_alive(id) {
+            trace!("Killing the agent {} on system {}", id, self.id);
+            Some(self.remove_agent(id.index))
+        } else {
+            None
+        }
     }
 
-    #[inline]
-    pub fn get_nb_agents(&self) -> usize {
-        self.agents.len()
+    /// Remove every agent matching the predicate and return how many have been removed.
+    pub fn kill_where<P>(&mut self, predicate: P) -> usize
+        where P: Fn(&A) -> bool
+    {
+        let agents_to_kill: Vec<usize> = self.agents
+            .iter()
+            .filter(|&(_, agent)| predicate(agent))
+            .map(|(key, _)| key)
+            .collect();
+
+        for &key in agents_to_kill.iter() {
+            self.remove_agent(key);
+        }
+
+        agents_to_kill.len()
     }
-}
 
-impl<'a, A: Agent<C=C>, C: Content>System<'a> for AgentSystem<A, C> {
-    type SystemData = ();
+    pub fn is_alive(&self, id: AgentId) -> bool {
+        self.agents.contains(id.index) && self.generations[id.index] == id.generation
+    }
 
-    fn run(&mut self, _: Self::SystemData) {
-        self.process_agent();
-        self.send_agents_messages();
-        self.collect_messages();
-        self.distribute_messages_collected_to_the_agents();
+    fn remove_agent(&mut self, key: usize) -> A {
+        let mut agent = self.take_agent(key);
+        agent.on_stop();
+        agent
     }
-}
 
+    fn take_agent(&mut self, key: usize) -> A {
+        let agent = self.agents.remove(key);
+        let id = AgentId::new(key, self.generations[key]);
+        self.spawned_by.remove(&id);
+        self.received_replies.remove(&id);
+        self.subscriptions.remove(&id);
 
-#[cfg(test)]
-mod test_sytem {
+        let system_id = self.id;
+        for subscriptions in self.subscriptions.values_mut() {
+            subscriptions.retain(|s| s.request.sender != (system_id, id));
+        }
 
-    use shred::{DispatcherBuilder, Resources};
+        if let Some((supervisor_id, index)) = self.supervised.remove(&id) {
+            if let Child::Worker { ref mut agent, .. } = self.supervisors[supervisor_id].children[index] {
+                *agent = None;
+            }
+        }
 
-    use super::*;
+        self.generations[key] = self.generations[key].wrapping_add(1);
+        agent
+    }
 
-    #[derive(Serialize, Deserialize, Clone, Debug)]
-    struct Person {
-        id: usize,
+    fn insert_migrated_agent(&mut self, mut agent: A, replies: Vec<(Id, ReplyWindow)>) -> AgentId {
+        let id = self.next_agent_id();
+        agent.set_id(id);
+        if !replies.is_empty() {
+            self.received_replies.insert(id, replies.into_iter().collect());
+        }
+        self.insert_agent(id, agent)
     }
 
-    impl Content for Person {}
+    /// Forward to the new address of the agent the messages received during its migration,
+    /// and those which will arrive at its old address.
+    fn complete_migration(&mut self, origin: AgentId, system_id: SystemId, agent_id: AgentId) {
+        trace!("The agent {} is now the agent {} of the system {}", origin, agent_id, system_id);
+        if let Some(migration) = self.migrations.remove(&origin) {
+            self.outbox.extend(migration.messages
+                .into_iter()
+                .map(|m| readdress(m, system_id, agent_id)));
+        }
 
-    #[derive(Serialize, Deserialize, Clone, Debug)]
-    enum Protocol{
-        Foo,
+        let until = Timestamp::now() + self.forwarding_duration;
+        self.forwards.insert(origin, Forward { system_id, agent_id, until });
     }
 
-    impl Content for Protocol {}
+    /// Reinstantiate in this system the agent whose migration to `to` failed.
+    fn bring_back(&mut self, origin: AgentId, to: SystemId) {
+        let agent = match (self.migrations.get_mut(&origin), self.agent_decoder) {
+            (Some(migration), Some(decode)) => decode(&migration.agent).map(|agent| (agent, mem::take(&mut migration.replies))),
+            _ => None,
+        };
 
-    impl Agent for Person {
-        type C = Protocol;
+        if let Some((agent, replies)) = agent {
+            let agent_id = self.insert_migrated_agent(agent, replies);
+            let sys_id = self.id;
+            self.agents[agent_id.index].on_error(&AgentError::MigrationFailed { to });
+            self.complete_migration(origin, sys_id, agent_id);
+        } else if let Some(migration) = self.migrations.remove(&origin) {
+            error!("The agent {} has been lost during its migration", origin);
+            for m in migration.messages {
+                send_to_dead_letters(&self.dead_letters, m);
+            }
+        }
+    }
 
-        fn id(&self) -> usize { self.id }
+    /// New address of an agent which has migrated from this system.
+    pub fn get_migrated_address(&self, id: AgentId) -> Option<(SystemId, AgentId)> {
+        self.forwards.get(&id).map(|forward| (forward.system_id, forward.agent_id))
+    }
 
-        fn set_id(&mut self, id: usize) { self.id = id }
+    /// Time given to the destination to confirm the arrival of a migrating agent before it comes back.
+    pub fn set_migration_timeout(&mut self, timeout: Duration) {
+        self.migration_timeout = timeout;
+    }
 
-        fn is_dead(&self) -> bool { false }
+    /// Time during which the messages sent to the old address of a migrated agent are forwarded.
+    pub fn set_forwarding_duration(&mut self, duration: Duration) {
+        self.forwarding_duration = duration;
+    }
 
-        fn handle_message(&mut self, _: &Message<Self::C>) {}
+    /// Bring back the agents whose arrival hasn't been confirmed in time, and forget the expired forwards.
+    pub fn check_migrations(&mut self) {
+        let now = Timestamp::now();
+        let expired: Vec<(AgentId, SystemId)> = self.migrations
+            .iter()
+            .filter(|&(_, migration)| migration.deadline <= now)
+            .map(|(&origin, migration)| (origin, migration.to))
+            .collect();
+
+        for (origin, to) in expired {
+            warn!("The system {} hasn't confirmed the arrival of the agent {}", to, origin);
+            self.bring_back(origin, to);
+        }
 
-        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
-            None
+        let now = Timestamp::now();
+        self.forwards.retain(|_, forward| forward.until > now);
+    }
+
+    /// Call `on_start` on the agents which haven't acted yet in this system.
+    fn start_agents(&mut self) {
+        for id in self.starting.drain(..) {
+            if self.generations[id.index] == id.generation {
+                if let Some(agent) = self.agents.get_mut(id.index) {
+                    agent.on_start();
+                }
+            }
         }
     }
 
-    struct PersonFactory;
+    /// Send the message of the local agent, unless it replies to a message the agent didn't
+    /// receive or breaks the protocol of its conversation. Returns true if the message is sent.
+    fn send_message(&mut self, agent_id: AgentId, mut m: Message<C>, now: Timestamp) -> bool {
+        let agent = match self.agents.get_mut(agent_id.index) {
+            Some(agent) if self.generations[agent_id.index] == agent_id.generation => agent,
+            _ => {
+                warn!("The agent {} is no longer alive to send the message {}", agent_id, m.id);
+                send_to_dead_letters(&self.dead_letters, m);
+                return false
+            },
+        };
 
-    impl AgentFactory<Person> for PersonFactory {
-        fn create(&self, agent_id: usize) -> Person {
-            Person {
-                id: agent_id,
+        if let Some(in_reply_to) = m.in_reply_to {
+            let received = self.received_replies
+                .get(&agent_id)
+                .is_some_and(|replies| replies.contains_key(&in_reply_to));
+
+            if !received {
+                warn!("The agent {} replied to the unknown message {}", agent_id, in_reply_to);
+                agent.on_error(&AgentError::InvalidReply { message_id: m.id, in_reply_to });
+                return false
             }
         }
+
+        let conforms = match agent.conformance() {
+            Some(checker) => {
+                let conforms = checker.check_sent(&m);
+                forget_finished_conversation(checker, &m);
+                conforms
+            },
+            None => true,
+        };
+        if !conforms {
+            agent.on_error(&AgentError::ProtocolViolation { message_id: m.id });
+            return false
+        }
+
+        if let Some(deadline) = m.reply_by {
+            let reply_with = *m.reply_with.get_or_insert_with(new_id);
+            self.pending_replies.insert(reply_with, PendingReply { agent_id, message_id: m.id, deadline });
+        }
+
+        let occurred = SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap_or(Duration::new(0,0));
+
+        m.set_sender((self.id, agent.id()));
+        m.set_occurred(occurred.as_secs());
+        if let Some(conversations) = agent.conversations() {
+            conversations.record_sent(&m, now);
+        }
+        self.outbox.push(m);
+        true
     }
 
+    pub fn process_agent(&mut self) {
+        self.start_agents();
+
+        let now = Timestamp::now();
+
+        let mut sent = Vec::new();
+        let mut failures = Vec::new();
+        let mut notifications = Vec::new();
+
+        for (key, agent) in self.agents.iter_mut() {
+            match panic::catch_unwind(AssertUnwindSafe(|| act(agent, now))) {
+                Ok(Some(messages)) => sent.push((AgentId::new(key, self.generations[key]), messages)),
+                Ok(None) => {},
+                Err(panic) => {
+                    // The state of the agent can't be trusted anymore.
+                    failures.push((AgentId::new(key, self.generations[key]), panic));
+                    continue;
+                },
+            }
 
-    #[test]
-    fn it_should_spawn_an_agent() {
-        let mut pers_sys: AgentSystem<Person, Protocol>;
-        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
-        pers_sys = AgentSystem::new(0, Box::new(PersonFactory), addr);
+            if let Some(timers) = agent.timers() {
+                for request in timers.take_requests() {
+                    match request {
+                        TimerRequest::Schedule { id, delay, mut message } => {
+                            message.set_sender((self.id, agent.id()));
+                            self.timers.schedule(id, delay, message, now);
+                        },
+                        TimerRequest::Cancel(id) => {
+                            self.timers.cancel(id);
+                        },
+                    }
+                }
+            }
 
-        pers_sys.spawn_agent();
-        pers_sys.spawn_agent();
+            let publisher = AgentId::new(key, self.generations[key]);
+            notifications.extend(agent.notifications().into_iter().map(|(topic, content)| (publisher, topic, content)));
+        }
 
-        assert_eq!(2, pers_sys.get_nb_agents());
+        for (agent_id, messages) in sent {
+            for m in messages {
+                self.send_message(agent_id, m, now);
+            }
+        }
+        self.outbox.sort_by_key(|m| Reverse(m.priority));
+
+        for (publisher, topic, content) in notifications {
+            self.notify_subscribers(publisher, &topic, content);
+        }
+
+        for (agent_id, panic) in failures {
+            self.supervise(agent_id, None, panic);
+        }
     }
 
-    #[test]
-    fn it_should_spawn_a_swarm_of_agents() {
-        let nb_agent_to_spawn = 10;
-        let mut pers_sys: AgentSystem<Person, Protocol>;
-        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    /// Apply the supervision strategy to an agent which panicked.
+    fn supervise(&mut self, agent_id: AgentId, message_id: Option<Uuid>, panic: Box<dyn Any + Send>) {
+        let failure = AgentFailure {
+            system_id: self.id,
+            agent_id,
+            message_id,
+            reason: panic_reason(&*panic),
+        };
 
-        pers_sys = AgentSystem::new(0, Box::new(PersonFactory), addr);
+        error!("The agent {} of the system {} panicked: {}", agent_id, self.id, failure.reason);
+        if let Some(ref sink) = self.failures {
+            if let Err(e) = sink.send(failure) {
+                error!("{}", e);
+            }
+        }
 
-        pers_sys.spawn_swarm(nb_agent_to_spawn);
+        // An earlier failure of the same tick may have already stopped or restarted the agent.
+        if !self.is_alive(agent_id) {
+            trace!("The agent {} of the system {} is already stopped", agent_id, self.id);
+            return;
+        }
 
-        assert_eq!(nb_agent_to_spawn, pers_sys.get_nb_agents());
+        if let Some(&(supervisor_id, index)) = self.supervised.get(&agent_id) {
+            self.remove_agent(agent_id.index);
+            self.handle_child_failure(supervisor_id, index);
+            return;
+        }
+
+        match self.supervision {
+            SupervisionStrategy::Restart => {
+                if let Some(agent) = self.recreate_agent(agent_id) {
+                    trace!("Restarting the agent {} of the system {}", agent_id, self.id);
+                    mem::replace(&mut self.agents[agent_id.index], agent).on_stop();
+                    self.starting.push(agent_id);
+                } else {
+                    warn!("The agent {} of the system {} can't be restarted, it's stopped", agent_id, self.id);
+                    self.remove_agent(agent_id.index);
+                }
+            },
+            SupervisionStrategy::Stop => {
+                self.remove_agent(agent_id.index);
+            },
+            SupervisionStrategy::Escalate => panic::resume_unwind(panic),
+        }
+    }
+
+    fn recreate_agent(&self, id: AgentId) -> Option<A> {
+        match self.spawned_by.get(&id) {
+            Some(None) => Some(self.factory.create(id)),
+            Some(Some(name)) => self.factories.get(name).map(|factory| factory.create(id)),
+            None => None,
+        }
+    }
+
+    /// Remove from the system every agent reporting `is_dead` and free its slot.
+    pub fn reap_dead_agents(&mut self) {
+        let dead_agents: Vec<usize> = self.agents
+            .iter()
+            .filter(|&(_, agent)| agent.is_dead())
+            .map(|(key, _)| key)
+            .collect();
+
+        for key in dead_agents {
+            trace!("Reaping the dead agent {} on system {}", self.agents[key].id(), self.id);
+            self.agents[key].on_terminate();
+            self.take_agent(key);
+        }
+    }
+
+    pub fn send_agents_messages(&mut self) {
+        let messages = self.outbox.drain(..);
+        self.dispatcher.dispatch_messages(messages);
+    }
+
+    /// Ask another system to spawn `count` agents with its factory. The ids of the agents
+    /// spawned can be retrieved with `take_remote_spawn_reply` once the reply is collected.
+    pub fn request_remote_spawn(&mut self, system_id: SystemId, count: usize) -> Uuid {
+        trace!("Requesting {} agents to the system {}", count, system_id);
+        let request_id = Uuid::new_v4();
+        self.controls.push(Control::new(
+            self.id,
+            system_id,
+            ControlMessage::SpawnAgents { request_id, count },
+        ));
+
+        request_id
+    }
+
+    pub fn take_remote_spawn_reply(&mut self, request_id: Uuid) -> Option<Vec<AgentId>> {
+        self.remote_spawns.remove(&request_id)
+    }
+
+    pub fn send_control_messages(&mut self) {
+        let controls = self.controls.drain(..);
+        self.dispatcher.dispatch_controls(controls);
+    }
+
+    pub fn process_control_messages(&mut self) {
+        for control in self.collector.drain_controls() {
+            match control.message {
+                ControlMessage::SpawnAgents { request_id, count } => {
+                    trace!("The system {} requests {} agents", control.sender, count);
+                    let agents = self.spawn_swarm(count);
+                    self.controls.push(Control::new(
+                        self.id,
+                        control.sender,
+                        ControlMessage::AgentsSpawned { request_id, agents },
+                    ));
+                },
+                ControlMessage::AgentsSpawned { request_id, agents } => {
+                    self.remote_spawns.insert(request_id, agents);
+                },
+                ControlMessage::MigrateAgent { origin, agent, replies } => {
+                    let reply = match self.agent_decoder.and_then(|decode| decode(&agent)) {
+                        Some(agent) => {
+                            let agent_id = self.insert_migrated_agent(agent, replies);
+                            ControlMessage::AgentMigrated { origin, agent_id }
+                        },
+                        None => {
+                            warn!("The system {} can't receive the agent {} from {}", self.id, origin, control.sender);
+                            ControlMessage::MigrationFailed { origin }
+                        },
+                    };
+                    self.controls.push(Control::new(self.id, control.sender, reply));
+                },
+                ControlMessage::AgentMigrated { origin, agent_id } => {
+                    if self.migrations.contains_key(&origin) {
+                        self.complete_migration(origin, control.sender, agent_id);
+                    } else {
+                        warn!("The agent {} reached the system {} after its migration timed out", origin, control.sender);
+                        let cancel = ControlMessage::MigrationCancelled { agent_id };
+                        self.controls.push(Control::new(self.id, control.sender, cancel));
+                    }
+                },
+                ControlMessage::MigrationFailed { origin } => {
+                    // The agent comes back in this system.
+                    self.bring_back(origin, control.sender);
+                },
+                ControlMessage::MigrationCancelled { agent_id } => {
+                    if self.is_alive(agent_id) {
+                        trace!("The system {} took back the agent {}", control.sender, agent_id);
+                        self.remove_agent(agent_id.index);
+                    }
+                },
+            }
+        }
+    }
+
+    pub fn collect_messages(&mut self) {
+        self.collector.collect_messages();
+    }
+
+    pub fn distribute_messages_collected_to_the_agents(&mut self) {
+        let mut messages: Vec<Message<C>> = match self.collector.drain_inbox() {
+            Some(messages) => messages.collect(),
+            None => return,
+        };
+        messages.sort_by_key(|m| Reverse(m.priority));
+
+        for m in messages {
+            match m.recipient {
+                Recipient::Agent{ system_id: _, agent_id } => self.deliver_message(agent_id, m),
+                Recipient::Broadcast{ system_id: _ } => {
+                    if m.performative == Performative::Cancel {
+                        let publishers: Vec<AgentId> = self.subscriptions.keys().cloned().collect();
+                        for publisher in publishers {
+                            self.cancel_subscription(publisher, &m);
+                        }
+                    }
+
+                    let recipients: Vec<AgentId> = self.agents
+                        .iter()
+                        .map(|(key, _)| AgentId::new(key, self.generations[key]))
+                        .collect();
+
+                    // An agent stopped by the failure of another one doesn't receive the message.
+                    for agent_id in recipients {
+                        if self.is_alive(agent_id) {
+                            self.receive_message(agent_id, &m, false);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn deliver_message(&mut self, agent_id: AgentId, m: Message<C>) {
+        let reminder = self.reminders.remove(&m.id);
+
+        if let Some(migration) = self.migrations.get_mut(&agent_id) {
+            trace!("Hold the message {} until the agent {} reaches the system {}", m.id, agent_id, migration.to);
+            migration.messages.push(m);
+            return
+        }
+
+        if let Some(forward) = self.forwards.get(&agent_id) {
+            self.outbox.push(readdress(m, forward.system_id, forward.agent_id));
+            return
+        }
+
+        if m.performative == Performative::Cancel {
+            self.cancel_subscription(agent_id, &m);
+        }
+
+        match self.agents.get(agent_id.index) {
+            Some(_) if self.generations[agent_id.index] == agent_id.generation => {
+                self.receive_message(agent_id, &m, reminder);
+            },
+            Some(_) => {
+                trace!("Reject the message {} addressed to the stale agent {}", m.id, agent_id);
+                self.nb_stale_messages += 1;
+                self.report_undeliverable_message(&m, agent_id);
+                send_to_dead_letters(&self.dead_letters, m);
+            },
+            None => {
+                if self.generations.get(agent_id.index).is_some_and(|&generation| generation != agent_id.generation) {
+                    trace!("Reject the message {} addressed to the removed agent {}", m.id, agent_id);
+                    self.nb_stale_messages += 1;
+                }
+                self.report_undeliverable_message(&m, agent_id);
+                send_to_dead_letters(&self.dead_letters, m);
+            },
+        }
+    }
+
+    /// Hand the message to the live agent, unless the agent sent it or it breaks the protocol
+    /// of its conversation. Subscriptions are handled by the system for the agent.
+    fn receive_message(&mut self, agent_id: AgentId, m: &Message<C>, reminder: bool) {
+        let now = Timestamp::now();
+        let agent = &mut self.agents[agent_id.index];
+
+        let own = agent.id() == m.sender.1 && self.id == m.sender.0;
+        if (own && !reminder) || !conforms_on_receipt(agent, m) {
+            return;
+        }
+
+        let window = match (m.reply_by, m.conversation_id) {
+            (Some(deadline), _) => ReplyWindow::Until(deadline),
+            (None, Some(conversation_id)) if agent.conversations().is_some() => ReplyWindow::Conversation(conversation_id),
+            (None, _) => ReplyWindow::Until(now + self.reply_window),
+        };
+        record_received_message(&mut self.received_replies, &mut self.pending_replies, agent_id, m, window);
+        if let Some(conversations) = agent.conversations() {
+            conversations.record_received(m, now);
+        }
+
+        match m.performative {
+            Performative::Subscribe | Performative::RequestWhenever => {
+                match panic::catch_unwind(AssertUnwindSafe(|| agent.accept_subscription(m))) {
+                    Ok(topic) => self.subscribe(agent_id, topic, m.clone()),
+                    Err(panic) => self.supervise(agent_id, Some(m.id), panic),
+                }
+            },
+            _ => {
+                if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(|| handle_received_message(agent, m))) {
+                    self.supervise(agent_id, Some(m.id), panic);
+                }
+            },
+        }
+    }
+
+    /// Register the subscription accepted by the publisher under the topic, and answer the subscriber.
+    fn subscribe(&mut self, publisher: AgentId, topic: Option<String>, request: Message<C>) {
+        let performative = if topic.is_some() { Performative::Agree } else { Performative::Refuse };
+        let answer = request.create_reply(performative, request.content.clone());
+        if !self.send_message(publisher, answer, Timestamp::now()) {
+            return;
+        }
+
+        if let Some(topic) = topic {
+            trace!("The agent {} subscribed to {} of the agent {}", request.sender.1, topic, publisher);
+            self.subscriptions.entry(publisher).or_default().push(Subscription { topic, request });
+        }
+    }
+
+    /// Remove the subscription the `Cancel` message refers to by its conversation, or every
+    /// subscription of its sender when it has no conversation.
+    fn cancel_subscription(&mut self, publisher: AgentId, cancel: &Message<C>) {
+        if let Some(subscriptions) = self.subscriptions.get_mut(&publisher) {
+            subscriptions.retain(|s| {
+                s.request.sender != cancel.sender
+                    || cancel.conversation_id.is_some_and(|id| s.request.conversation_id != Some(id))
+            });
+        }
+    }
+
+    /// Send an `Inform` with the content to every subscriber of the topic of the publisher,
+    /// and return how many have been notified. Subscribers which died in this system are forgotten.
+    pub fn notify_subscribers(&mut self, publisher: AgentId, topic: &str, content: C) -> usize {
+        let mut subscriptions = match self.subscriptions.remove(&publisher) {
+            Some(subscriptions) => subscriptions,
+            None => return 0,
+        };
+
+        subscriptions.retain(|s| s.request.sender.0 != self.id || self.is_alive(s.request.sender.1));
+
+        let now = Timestamp::now();
+        let mut nb_notified = 0;
+        for subscription in subscriptions.iter().filter(|s| s.topic == topic) {
+            let notification = Message::reply_to(&subscription.request).content(content.clone()).build();
+            if self.send_message(publisher, notification, now) {
+                nb_notified += 1;
+            }
+        }
+
+        if !subscriptions.is_empty() {
+            self.subscriptions.insert(publisher, subscriptions);
+        }
+
+        nb_notified
+    }
+
+    /// Number of subscriptions accepted by the agent.
+    pub fn get_nb_subscriptions(&self, publisher: AgentId) -> usize {
+        self.subscriptions.get(&publisher).map_or(0, |subscriptions| subscriptions.len())
+    }
+
+    /// Tell the sender of the message, if it lives in this system, that the message is lost.
+    fn report_undeliverable_message(&mut self, m: &Message<C>, recipient: AgentId) {
+        let (system_id, sender) = m.sender;
+
+        if system_id == self.id && self.is_alive(sender) {
+            self.agents[sender.index].on_error(&AgentError::UndeliverableMessage { message_id: m.id, recipient });
+        }
+    }
+
+    /// Time during which an agent can reply to a message it received, when the message has no
+    /// `reply_by` deadline and the agent doesn't follow its conversation.
+    pub fn set_reply_window(&mut self, window: Duration) {
+        self.reply_window = window;
+    }
+
+    /// Forget the received messages whose reply window is over, except the subscriptions the
+    /// agents still notify.
+    pub fn forget_expired_replies(&mut self) {
+        let now = Timestamp::now();
+        let subscriptions = &self.subscriptions;
+        let agents = &mut self.agents;
+        for (agent_id, replies) in self.received_replies.iter_mut() {
+            let subscriptions = subscriptions.get(agent_id);
+            let conversations = agents.get_mut(agent_id.index).and_then(|agent| agent.conversations());
+            replies.retain(|&reply_with, &mut window| {
+                let open = match window {
+                    ReplyWindow::Until(until) => until > now,
+                    ReplyWindow::Conversation(id) => match conversations {
+                        Some(ref conversations) => conversations.get(id).is_some(),
+                        None => false,
+                    },
+                };
+
+                open || subscriptions.is_some_and(|subscriptions| {
+                    subscriptions.iter().any(|s| s.request.reply_with == Some(reply_with))
+                })
+            });
+        }
+        self.received_replies.retain(|_, replies| !replies.is_empty());
+    }
+
+    /// Forget in the conformance checkers the conversations the agents removed from their table,
+    /// once expired or over.
+    pub fn forget_removed_conversations(&mut self) {
+        for (_, agent) in self.agents.iter_mut() {
+            let followed: Vec<Id> = match agent.conformance() {
+                Some(checker) => checker.conversations().cloned().collect(),
+                None => continue,
+            };
+            let removed: Vec<Id> = match agent.conversations() {
+                Some(conversations) => followed.into_iter().filter(|&id| conversations.get(id).is_none()).collect(),
+                None => continue,
+            };
+
+            if let Some(checker) = agent.conformance() {
+                for conversation_id in removed {
+                    checker.forget(conversation_id);
+                }
+            }
+        }
+    }
+
+    /// Notify the agents whose messages haven't been replied before their `reply_by` deadline.
+    pub fn check_reply_deadlines(&mut self) {
+        let now = Timestamp::now();
+        let expired: Vec<Id> = self.pending_replies
+            .iter()
+            .filter(|&(_, pending)| pending.deadline <= now)
+            .map(|(&reply_with, _)| reply_with)
+            .collect();
+
+        for reply_with in expired {
+            let pending = self.pending_replies.remove(&reply_with).expect("Should be a pending reply");
+
+            if self.is_alive(pending.agent_id) {
+                trace!("No reply to the message {} of the agent {}", pending.message_id, pending.agent_id);
+                self.agents[pending.agent_id.index].on_error(&AgentError::ReplyTimeout {
+                    message_id: pending.message_id,
+                    reply_with,
+                });
+            }
+        }
+    }
+
+    /// Send the message of the agent once the delay has elapsed, returns the handle to cancel it.
+    pub fn schedule(&mut self, sender: AgentId, delay: Delay, mut message: Message<C>) -> TimerId {
+        let id = new_id();
+        message.set_sender((self.id, sender));
+        self.timers.schedule(id, delay, message, Timestamp::now());
+        id
+    }
+
+    /// Cancel a scheduled message, returns false if it's already sent or cancelled.
+    pub fn cancel_timer(&mut self, id: TimerId) -> bool {
+        self.timers.cancel(id).is_some()
+    }
+
+    /// Move the timers by one tick and send the messages they deliver, as their sender would.
+    pub fn fire_timers(&mut self) {
+        let now = Timestamp::now();
+
+        for m in self.timers.advance(now) {
+            let (system_id, sender) = m.sender;
+            if system_id != self.id {
+                warn!("The timer of the message {} belongs to the system {}", m.id, system_id);
+                send_to_dead_letters(&self.dead_letters, m);
+                continue;
+            }
+
+            let message_id = m.id;
+            let reminder = m.recipient == Recipient::Agent { system_id, agent_id: sender };
+            if self.send_message(sender, m, now) && reminder {
+                self.reminders.insert(message_id);
+            }
+        }
+    }
+
+    /// Save the timers not fired yet, to schedule them again with `restore_timers`.
+    /// Timers in ticks keep the number of ticks left, the others their deadline.
+    pub fn checkpoint_timers(&self) -> Result<Vec<u8>, Box<bincode::ErrorKind>> {
+        bincode::serialize(&self.timers.pending())
+    }
+
+    /// Schedule the timers saved by `checkpoint_timers`, returns how many have been restored.
+    /// A timer still pending is scheduled again from the checkpoint. The timers of agents which
+    /// aren't alive in this system are sent to the dead letters.
+    pub fn restore_timers(&mut self, checkpoint: &[u8]) -> Result<usize, Box<bincode::ErrorKind>> {
+        let pending: Vec<PendingTimer<C>> = bincode::deserialize(checkpoint)?;
+        let now = Timestamp::now();
+        let mut nb_timers = 0;
+
+        for timer in pending {
+            let (system_id, sender) = timer.message.sender;
+            if system_id != self.id || !self.is_alive(sender) {
+                warn!("The agent {} of the system {} isn't there to restore its timer", sender, system_id);
+                send_to_dead_letters(&self.dead_letters, timer.message);
+                continue;
+            }
+
+            self.timers.schedule(timer.id, timer.due, timer.message, now);
+            nb_timers += 1;
+        }
+        Ok(nb_timers)
+    }
+
+    pub fn add_local_observer_system(&mut self, system_id: SystemId, channel_sender: Sender<Packet<C>>) {
+        trace!("Adding the local observer system {}", system_id);
+        self.dispatcher.add_local_sender(system_id, channel_sender);
+    }
+
+    pub fn add_remote_observer_system(&mut self, rs_id: SystemId, rs_addr: SocketAddr) {
+        trace!("Adding the remote observer system {} - {}", rs_id, rs_addr);
+        self.collector.add_remote_collector(rs_addr);
+    }
+
+    /// Messages addressed to an agent that no longer exists are sent to this sink
+    /// instead of being silently dropped.
+    pub fn set_dead_letters_sink(&mut self, sink: Sender<Message<C>>) {
+        self.dead_letters = Some(sink);
+    }
+
+    /// Set what the system does with the agents which panic. Defaults to `Stop`.
+    pub fn set_supervision_strategy(&mut self, strategy: SupervisionStrategy) {
+        self.supervision = strategy;
+    }
+
+    /// Every panic of an agent is reported to this sink.
+    pub fn set_failures_sink(&mut self, sink: Sender<AgentFailure>) {
+        self.failures = Some(sink);
+    }
+
+    #[inline]
+    pub fn get_sender(&self) -> Sender<Packet<C>> {
+        self.sender.clone()
+    }
+
+    #[inline]
+    pub fn get_nb_message_inbox(&self) -> usize {
+        self.outbox.len()
+    }
+
+    #[inline]
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    #[inline]
+    pub fn get_nb_agents(&self) -> usize {
+        self.agents.len()
+    }
+
+    /// Number of messages sent with a `reply_by` deadline still waiting for their reply.
+    #[inline]
+    pub fn get_nb_pending_replies(&self) -> usize {
+        self.pending_replies.len()
+    }
+
+    /// Number of scheduled messages not sent yet.
+    #[inline]
+    pub fn get_nb_timers(&self) -> usize {
+        self.timers.len()
+    }
+
+    /// Number of messages rejected because they were addressed to a stale agent id.
+    #[inline]
+    pub fn get_nb_stale_messages(&self) -> usize {
+        self.nb_stale_messages
+    }
+}
+
+impl <A, C>AgentSystem<A, C>
+    where A: Agent<C=C> + Serialize + DeserializeOwned, C: Content
+{
+    /// Allow the system to reinstantiate the agents migrating from other systems.
+    pub fn enable_migration(&mut self) {
+        self.agent_decoder = Some(decode_agent::<A>);
+    }
+
+    /// Move the agent with its state to another system. Messages addressed to its current id
+    /// are then forwarded to its new address. The migration must be enabled, to bring the agent
+    /// back if it fails.
+    pub fn migrate_agent(&mut self, id: AgentId, to: SystemId) -> bool {
+        if !self.is_alive(id) || to == self.id {
+            return false
+        }
+
+        if self.agent_decoder.is_none() {
+            warn!("The migration is disabled in the system {}", self.id);
+            return false
+        }
+
+        let sys_id = self.id;
+        self.agents[id.index].on_migrate(sys_id, to);
+
+        let agent = match bincode::serialize(&self.agents[id.index]) {
+            Ok(agent) => agent,
+            Err(e) => {
+                error!("Can't serialize the agent {}: {}", id, e);
+                self.agents[id.index].on_error(&AgentError::MigrationFailed { to });
+                return false
+            },
+        };
+
+        trace!("Migrating the agent {} from the system {} to {}", id, self.id, to);
+        let replies: Vec<(Id, ReplyWindow)> = self.received_replies
+            .get(&id)
+            .map(|replies| replies.iter().map(|(&reply_with, &window)| (reply_with, window)).collect())
+            .unwrap_or_default();
+        self.take_agent(id.index);
+        self.controls.push(Control::new(
+            self.id,
+            to,
+            ControlMessage::MigrateAgent { origin: id, agent: agent.clone(), replies: replies.clone() },
+        ));
+        let deadline = Timestamp::now() + self.migration_timeout;
+        self.migrations.insert(id, Migration { to, agent, replies, messages: Vec::new(), deadline });
+
+        true
+    }
+}
+
+impl<'a, A: Agent<C=C>, C: Content>System<'a> for AgentSystem<A, C> {
+    type SystemData = ();
+
+    fn run(&mut self, _: Self::SystemData) {
+        self.fire_timers();
+        self.process_agent();
+        self.reap_dead_agents();
+        self.send_agents_messages();
+        self.send_control_messages();
+        self.collect_messages();
+        self.process_control_messages();
+        self.check_migrations();
+        self.distribute_messages_collected_to_the_agents();
+        self.check_reply_deadlines();
+        self.forget_expired_replies();
+        self.forget_removed_conversations();
+    }
+}
+
+fn decode_agent<A: DeserializeOwned>(agent: &[u8]) -> Option<A> {
+    bincode::deserialize(agent).ok()
+}
+
+fn readdress<C>(mut message: Message<C>, system_id: SystemId, agent_id: AgentId) -> Message<C> {
+    message.recipient = Recipient::Agent { system_id, agent_id };
+    message
+}
+
+/// Run the behaviours of the agent, or call `act` if it has none.
+fn act<A: Agent>(agent: &mut A, now: Timestamp) -> Option<Vec<Message<A::C>>> {
+    match agent.behaviours() {
+        Some(behaviours) => Some(behaviours.run(now)),
+        None => agent.act(),
+    }
+}
+
+/// Queue the message in the mailbox of the agent, or let the agent handle it then post it to its behaviours.
+fn handle_received_message<A: Agent>(agent: &mut A, message: &Message<A::C>) {
+    if let Some(mailbox) = agent.mailbox() {
+        mailbox.push(message.clone());
+        return;
+    }
+
+    agent.handle_message(message);
+    if let Some(behaviours) = agent.behaviours() {
+        behaviours.post(message.clone());
+    }
+}
+
+/// Check the message received by the agent against the protocol of its conversation.
+fn conforms_on_receipt<A: Agent>(agent: &mut A, message: &Message<A::C>) -> bool {
+    match agent.conformance() {
+        Some(checker) => {
+            let conforms = checker.check_received(message);
+            forget_finished_conversation(checker, message);
+            conforms
+        },
+        None => true,
+    }
+}
+
+/// Stop following the conversation of the message once it's done with every peer.
+fn forget_finished_conversation<C>(checker: &mut ConformanceChecker, message: &Message<C>) {
+    if let Some(conversation_id) = message.conversation_id {
+        if checker.is_over(conversation_id) {
+            checker.forget(conversation_id);
+        }
+    }
+}
+
+fn record_received_message<C>(
+    received_replies: &mut HashMap<AgentId, HashMap<Id, ReplyWindow>>,
+    pending_replies: &mut HashMap<Id, PendingReply>,
+    agent_id: AgentId,
+    message: &Message<C>,
+    window: ReplyWindow,
+) {
+    if let Some(reply_with) = message.reply_with {
+        received_replies.entry(agent_id).or_default().insert(reply_with, window);
+    }
+
+    if let Some(in_reply_to) = message.in_reply_to {
+        if pending_replies.get(&in_reply_to).is_some_and(|pending| pending.agent_id == agent_id) {
+            pending_replies.remove(&in_reply_to);
+        }
+    }
+}
+
+fn send_to_dead_letters<C: Content>(dead_letters: &Option<Sender<Message<C>>>, message: Message<C>) {
+    match *dead_letters {
+        Some(ref sink) => {
+            if let Err(e) = sink.send(message) {
+                error!("{}", e);
+            }
+        },
+        None => trace!("Drop the message {} addressed to a dead agent", message.id),
+    }
+}
+
+#[cfg(test)]
+mod test_sytem {
+
+    use shred::{DispatcherBuilder, Resources};
+
+    use super::*;
+    use agent::BoxedAgent;
+    use agent_factory::BoxedAgentFactory;
+    use supervision::{RestartIntensity, RestartStrategy};
+    use conversation::{ConversationState, ConversationTable, InteractionProtocol};
+    use protocol::conformance::{ConformanceChecker, ViolationPolicy};
+    use behaviour::{Behaviours, Context, cyclic};
+    use mailbox::{Mailbox, MessageTemplate};
+    use timer::{Delay, Timers};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    struct Person {
+        id: AgentId,
+    }
+
+    impl Content for Person {}
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    enum Protocol{
+        Foo,
+    }
+
+    impl Content for Protocol {}
+
+    impl Agent for Person {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { false }
+
+        fn handle_message(&mut self, _: &Message<Self::C>) {}
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            None
+        }
+    }
+
+    struct PersonFactory;
+
+    impl AgentFactory<Person> for PersonFactory {
+        fn create(&self, agent_id: AgentId) -> Person {
+            Person {
+                id: agent_id,
+            }
+        }
+    }
+
+
+    #[test]
+    fn it_should_spawn_an_agent() {
+        let mut pers_sys: AgentSystem<Person, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        pers_sys = AgentSystem::new(0, Box::new(PersonFactory), addr);
+
+        pers_sys.spawn_agent();
+        pers_sys.spawn_agent();
+
+        assert_eq!(2, pers_sys.get_nb_agents());
+    }
+
+    #[test]
+    fn it_should_spawn_a_swarm_of_agents() {
+        let nb_agent_to_spawn = 10;
+        let mut pers_sys: AgentSystem<Person, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+        pers_sys = AgentSystem::new(0, Box::new(PersonFactory), addr);
+
+        pers_sys.spawn_swarm(nb_agent_to_spawn);
+
+        assert_eq!(nb_agent_to_spawn, pers_sys.get_nb_agents());
+    }
+
+    struct Mortal {
+        id: AgentId,
+        dead: bool,
+        terminated: Sender<usize>,
+    }
+
+    impl Agent for Mortal {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { self.dead }
+
+        fn handle_message(&mut self, _: &Message<Self::C>) {}
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            self.dead = true;
+            None
+        }
+
+        fn on_terminate(&mut self) {
+            self.terminated.send(self.id.index).expect("Should notify the termination");
+        }
+    }
+
+    struct MortalFactory(Sender<usize>);
+
+    impl AgentFactory<Mortal> for MortalFactory {
+        fn create(&self, agent_id: AgentId) -> Mortal {
+            Mortal {
+                id: agent_id,
+                dead: false,
+                terminated: self.0.clone(),
+            }
+        }
+    }
+
+    struct Witness {
+        id: AgentId,
+        events: Sender<&'static str>,
+        dead: bool,
+    }
+
+    impl Agent for Witness {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { self.dead }
+
+        fn handle_message(&mut self, _: &Message<Self::C>) {}
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            self.events.send("act").expect("Should send the event");
+            Some(vec! [
+                Message::inform(Recipient::Agent{ agent_id: AgentId::new(42, 0), system_id: 0 })
+                    .priority(1)
+                    .content(Protocol::Foo)
+                    .build()
+            ])
+        }
+
+        fn on_spawn(&mut self) { self.events.send("spawn").expect("Should send the event") }
+
+        fn on_start(&mut self) { self.events.send("start").expect("Should send the event") }
+
+        fn on_stop(&mut self) { self.events.send("stop").expect("Should send the event") }
+
+        fn on_terminate(&mut self) { self.events.send("terminate").expect("Should send the event") }
+
+        fn on_error(&mut self, error: &AgentError) {
+            match *error {
+                AgentError::UndeliverableMessage { .. } => self.events.send("error").expect("Should send the event"),
+                _ => panic!("Should not fail to migrate"),
+            }
+        }
+    }
+
+    struct WitnessFactory(Sender<&'static str>);
+
+    impl AgentFactory<Witness> for WitnessFactory {
+        fn create(&self, agent_id: AgentId) -> Witness {
+            Witness {
+                id: agent_id,
+                events: self.0.clone(),
+                dead: false,
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_call_the_lifecycle_hooks_of_an_agent() {
+        let (sender, events) = channel();
+        let mut system: AgentSystem<Witness, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+        system = AgentSystem::new(0, Box::new(WitnessFactory(sender)), addr);
+
+        let witness = system.spawn_agent();
+        system.run(());
+        system.run(());
+        system.kill_agent(witness);
+
+        assert_eq!(
+            vec!["spawn", "start", "act", "error", "act", "error", "stop"],
+            events.try_iter().collect::<Vec<_>>()
+        );
+
+        // A reaped agent is terminated, not stopped.
+        let witness = system.spawn_agent();
+        if let Some(witness) = system.agents.get_mut(witness.index) {
+            witness.dead = true;
+        }
+        system.run(());
+
+        assert_eq!(vec!["spawn", "start", "act", "terminate"], events.try_iter().collect::<Vec<_>>());
+    }
+
+    struct Faulty {
+        id: AgentId,
+        panic_on_act: bool,
+        nb_messages: usize,
+        timers: Timers<Protocol>,
+        stops: Option<Sender<AgentId>>,
+    }
+
+    impl Agent for Faulty {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { false }
+
+        fn handle_message(&mut self, _: &Message<Self::C>) {
+            self.nb_messages += 1;
+            if self.nb_messages > 1 {
+                panic!("Too many messages");
+            }
+        }
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            if self.panic_on_act {
+                let reminder = Message::inform((0, self.id)).content(Protocol::Foo).build();
+                self.timers.schedule(Delay::Ticks(1), reminder);
+                panic!("Can't act");
+            }
+            None
+        }
+
+        fn on_stop(&mut self) {
+            if let Some(ref stops) = self.stops {
+                stops.send(self.id).expect("Should send the stop");
+            }
+        }
+
+        fn timers(&mut self) -> Option<&mut Timers<Self::C>> {
+            Some(&mut self.timers)
+        }
+    }
+
+    struct FaultyFactory(bool);
+
+    impl AgentFactory<Faulty> for FaultyFactory {
+        fn create(&self, agent_id: AgentId) -> Faulty {
+            Faulty {
+                id: agent_id,
+                panic_on_act: self.0,
+                nb_messages: 0,
+                timers: Timers::new(),
+                stops: None,
+            }
+        }
+    }
+
+    fn message_to(agent_id: AgentId) -> Message<Protocol> {
+        let mut message = Message::inform(Recipient::Agent{ agent_id, system_id: 0 })
+            .priority(1)
+            .content(Protocol::Foo)
+            .build();
+        message.set_sender((1, AgentId::default()));
+        message
+    }
+
+    #[test]
+    fn it_should_stop_an_agent_which_panicked_while_acting() {
+        let (sink, failures) = channel();
+        let mut system: AgentSystem<Faulty, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+        system = AgentSystem::new(0, Box::new(FaultyFactory(true)), addr);
+        system.set_failures_sink(sink);
+        system.register_factory("healthy", Box::new(FaultyFactory(false)));
+
+        let faulty = system.spawn_agent();
+        let healthy = system.spawn_agent_of("healthy").expect("Should spawn an healthy agent");
+        system.run(());
+
+        let failure = failures.try_recv().expect("Should report the failure");
+
+        assert_eq!(faulty, failure.agent_id);
+        assert_eq!(None, failure.message_id);
+        assert_eq!("Can't act", failure.reason);
+        assert!(!system.is_alive(faulty));
+        assert!(system.is_alive(healthy));
+    }
+
+    #[test]
+    fn it_should_restart_an_agent_which_panicked_while_handling_a_message() {
+        let (sink, failures) = channel();
+        let mut system: AgentSystem<Faulty, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+        system = AgentSystem::new(0, Box::new(FaultyFactory(false)), addr);
+        system.set_failures_sink(sink);
+        system.set_supervision_strategy(SupervisionStrategy::Restart);
+
+        let faulty = system.spawn_agent();
+        let message = message_to(faulty);
+        let message_id = message.id;
+
+        system.get_sender().send(Packet::Agent(message_to(faulty))).expect("Should send the message");
+        system.get_sender().send(Packet::Agent(message)).expect("Should send the message");
+        system.run(());
+
+        let failure = failures.try_recv().expect("Should report the failure");
+
+        assert_eq!(faulty, failure.agent_id);
+        assert_eq!(Some(message_id), failure.message_id);
+        assert_eq!(0, system.kill_agent(faulty).expect("Should restart the agent").nb_messages);
+    }
+
+    struct StoppingFactory(Sender<AgentId>);
+
+    impl AgentFactory<Faulty> for StoppingFactory {
+        fn create(&self, agent_id: AgentId) -> Faulty {
+            Faulty { stops: Some(self.0.clone()), ..FaultyFactory(true).create(agent_id) }
+        }
+    }
+
+    #[test]
+    fn it_should_stop_the_failed_instance_of_a_restarted_agent_and_forget_its_requests() {
+        let (sender, stops) = channel();
+        let mut system: AgentSystem<Faulty, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+        system = AgentSystem::new(0, Box::new(StoppingFactory(sender)), addr);
+        system.set_supervision_strategy(SupervisionStrategy::Restart);
+
+        let faulty = system.spawn_agent();
+        system.run(());
+
+        assert!(system.is_alive(faulty));
+        assert_eq!(vec![faulty], stops.try_iter().collect::<Vec<_>>());
+        assert_eq!(0, system.get_nb_timers());
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't act")]
+    fn it_should_escalate_the_panic_of_an_agent() {
+        let mut system: AgentSystem<Faulty, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+        system = AgentSystem::new(0, Box::new(FaultyFactory(true)), addr);
+        system.set_supervision_strategy(SupervisionStrategy::Escalate);
+        system.spawn_agent();
+        system.run(());
+    }
+
+    fn supervisor_spec(strategy: RestartStrategy, max_restarts: usize, nb_workers: usize) -> SupervisorSpec<Faulty> {
+        let intensity = RestartIntensity { max_restarts, within: Duration::from_secs(60) };
+
+        (0..nb_workers).fold(SupervisorSpec::new(strategy, intensity), |spec, _| {
+            spec.worker(Box::new(FaultyFactory(false)))
+        })
+    }
+
+    fn crash(system: &mut AgentSystem<Faulty, Protocol>, agent_id: AgentId) {
+        system.get_sender().send(Packet::Agent(message_to(agent_id))).expect("Should send the message");
+        system.get_sender().send(Packet::Agent(message_to(agent_id))).expect("Should send the message");
+        system.run(());
+    }
+
+    fn supervised_system() -> AgentSystem<Faulty, Protocol> {
+        AgentSystem::new(0, Box::new(FaultyFactory(false)), SocketAddr::from(([127, 0, 0, 1], 0)))
+    }
+
+    #[test]
+    fn it_should_restart_only_the_failed_child_with_one_for_one() {
+        let mut system = supervised_system();
+        let supervisor = system.spawn_supervisor(supervisor_spec(RestartStrategy::OneForOne, 1, 3));
+        let before = system.get_supervised_agents(supervisor);
+
+        crash(&mut system, before[1]);
+        let after = system.get_supervised_agents(supervisor);
+
+        assert_eq!(3, after.len());
+        assert_eq!(before[0], after[0]);
+        assert_ne!(before[1], after[1]);
+        assert_eq!(before[2], after[2]);
+        assert!(!system.is_alive(before[1]));
+        assert!(system.is_alive(after[1]));
+    }
+
+    #[test]
+    fn it_should_restart_all_the_children_with_one_for_all() {
+        let mut system = supervised_system();
+        let supervisor = system.spawn_supervisor(supervisor_spec(RestartStrategy::OneForAll, 1, 3));
+        let before = system.get_supervised_agents(supervisor);
+
+        crash(&mut system, before[1]);
+        let after = system.get_supervised_agents(supervisor);
+
+        assert_eq!(3, system.get_nb_agents());
+        assert!(before.iter().all(|&id| !system.is_alive(id)));
+        assert!(after.iter().all(|&id| system.is_alive(id)));
+    }
+
+    #[test]
+    fn it_should_restart_all_the_children_once_when_they_fail_in_the_same_tick() {
+        let mut system = supervised_system();
+        let intensity = RestartIntensity { max_restarts: 1, within: Duration::from_secs(60) };
+        let spec = SupervisorSpec::new(RestartStrategy::OneForAll, intensity)
+            .worker(Box::new(FaultyFactory(true)))
+            .worker(Box::new(FaultyFactory(true)));
+        let supervisor = system.spawn_supervisor(spec);
+        let before = system.get_supervised_agents(supervisor);
+
+        system.run(());
+        let after = system.get_supervised_agents(supervisor);
+
+        assert!(system.is_supervisor_running(supervisor));
+        assert_eq!(2, system.get_nb_agents());
+        assert!(before.iter().all(|&id| !system.is_alive(id)));
+        assert!(after.iter().all(|&id| system.is_alive(id)));
+    }
+
+    #[test]
+    fn it_should_restart_the_children_started_after_the_failed_one_with_rest_for_one() {
+        let mut system = supervised_system();
+        let supervisor = system.spawn_supervisor(supervisor_spec(RestartStrategy::RestForOne, 1, 3));
+        let before = system.get_supervised_agents(supervisor);
+
+        crash(&mut system, before[1]);
+        let after = system.get_supervised_agents(supervisor);
+
+        assert_eq!(before[0], after[0]);
+        assert_ne!(before[1], after[1]);
+        assert_ne!(before[2], after[2]);
+        assert!(!system.is_alive(before[2]));
+        assert!(system.is_alive(after[2]));
+    }
+
+    #[test]
+    fn it_should_shut_down_a_tree_exceeding_its_restart_intensity() {
+        let mut system = supervised_system();
+        let supervisor = system.spawn_supervisor(supervisor_spec(RestartStrategy::OneForOne, 1, 2));
+
+        let first = system.get_supervised_agents(supervisor)[0];
+        crash(&mut system, first);
+        let second = system.get_supervised_agents(supervisor)[0];
+        crash(&mut system, second);
+
+        assert!(!system.is_supervisor_running(supervisor));
+        assert_eq!(0, system.get_nb_agents());
+    }
+
+    #[test]
+    fn it_should_escalate_the_failure_of_a_child_supervisor_to_its_parent() {
+        let mut system = supervised_system();
+        let spec = supervisor_spec(RestartStrategy::OneForOne, 1, 1)
+            .supervisor(supervisor_spec(RestartStrategy::OneForOne, 0, 1));
+        let supervisor = system.spawn_supervisor(spec);
+        let before = system.get_supervised_agents(supervisor);
+
+        crash(&mut system, before[1]);
+        let after = system.get_supervised_agents(supervisor);
+
+        assert!(system.is_supervisor_running(supervisor));
+        assert_eq!(before[0], after[0]);
+        assert_ne!(before[1], after[1]);
+        assert!(system.is_alive(after[1]));
+    }
+
+    #[test]
+    fn it_should_reap_dead_agents() {
+        let (sender, receiver) = channel();
+        let mut system: AgentSystem<Mortal, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+        system = AgentSystem::new(0, Box::new(MortalFactory(sender)), addr);
+        system.spawn_swarm(3);
+        system.run(());
+
+        assert_eq!(0, system.get_nb_agents());
+        assert_eq!(vec![0, 1, 2], receiver.try_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn it_should_send_messages_addressed_to_a_dead_agent_to_the_dead_letters() {
+        let (sink, dead_letters) = channel();
+        let mut system: AgentSystem<Person, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+        system = AgentSystem::new(0, Box::new(PersonFactory), addr);
+        system.set_dead_letters_sink(sink);
+        system.spawn_agent();
+
+        let message = Message::inform(Recipient::Agent{ agent_id: AgentId::new(42, 0), system_id: 0 })
+            .priority(1)
+            .content(Protocol::Foo)
+            .build();
+        let message_id = message.id;
+
+        system.get_sender().send(Packet::Agent(message)).expect("Should send the message");
+        system.collect_messages();
+        system.distribute_messages_collected_to_the_agents();
+
+        let dead_letter = dead_letters.try_recv().expect("Should receive a dead letter");
+        assert_eq!(message_id, dead_letter.id);
+    }
+
+    #[test]
+    fn it_should_kill_an_agent_from_its_id() {
+        let mut pers_sys: AgentSystem<Person, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        pers_sys = AgentSystem::new(0, Box::new(PersonFactory), addr);
+
+        let id = pers_sys.spawn_agent();
+        pers_sys.spawn_agent();
+
+        let killed = pers_sys.kill_agent(id).expect("Should kill the agent");
+
+        assert_eq!(id, killed.id());
+        assert!(!pers_sys.is_alive(id));
+        assert_eq!(1, pers_sys.get_nb_agents());
+    }
+
+    #[test]
+    fn it_should_not_kill_the_agent_reusing_the_slot_of_a_stale_id() {
+        let mut pers_sys: AgentSystem<Person, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        pers_sys = AgentSystem::new(0, Box::new(PersonFactory), addr);
+
+        let stale_id = pers_sys.spawn_agent();
+        pers_sys.kill_agent(stale_id);
+        let id = pers_sys.spawn_agent();
+
+        assert_eq!(stale_id.index, id.index);
+        assert_ne!(stale_id.generation, id.generation);
+        assert!(pers_sys.kill_agent(stale_id).is_none());
+        assert!(pers_sys.is_alive(id));
+    }
+
+    #[test]
+    fn it_should_reject_the_messages_addressed_to_a_stale_agent_id() {
+        let (sink, dead_letters) = channel();
+        let mut pers_sys: AgentSystem<Person, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        pers_sys = AgentSystem::new(0, Box::new(PersonFactory), addr);
+        pers_sys.set_dead_letters_sink(sink);
+
+        let stale_id = pers_sys.spawn_agent();
+        pers_sys.kill_agent(stale_id);
+        pers_sys.spawn_agent();
+
+        let message = Message::inform(Recipient::Agent{ agent_id: stale_id, system_id: 0 })
+            .priority(1)
+            .content(Protocol::Foo)
+            .build();
+
+        pers_sys.get_sender().send(Packet::Agent(message)).expect("Should send the message");
+        pers_sys.collect_messages();
+        pers_sys.distribute_messages_collected_to_the_agents();
+
+        assert_eq!(1, pers_sys.get_nb_stale_messages());
+        assert!(dead_letters.try_recv().is_ok());
+    }
+
+    #[test]
+    fn it_should_count_the_messages_addressed_to_a_removed_agent_whose_slot_is_empty() {
+        let mut pers_sys: AgentSystem<Person, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        pers_sys = AgentSystem::new(0, Box::new(PersonFactory), addr);
+
+        let stale_id = pers_sys.spawn_agent();
+        pers_sys.kill_agent(stale_id);
+
+        for agent_id in vec![stale_id, AgentId::new(stale_id.index, stale_id.generation + 1)] {
+            let message = Message::inform((0, agent_id)).content(Protocol::Foo).build();
+            pers_sys.get_sender().send(Packet::Agent(message)).expect("Should send the message");
+        }
+        pers_sys.collect_messages();
+        pers_sys.distribute_messages_collected_to_the_agents();
+
+        assert_eq!(1, pers_sys.get_nb_stale_messages());
+    }
+
+    struct Walker {
+        id: AgentId,
+        position: (u8, u8),
+    }
+
+    impl Agent for Walker {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { false }
+
+        fn handle_message(&mut self, _: &Message<Self::C>) {}
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            None
+        }
+    }
+
+    struct WalkerFactory;
+
+    impl AgentFactory<Walker> for WalkerFactory {
+        fn create(&self, agent_id: AgentId) -> Walker {
+            self.create_with(agent_id, (0, 0))
+        }
+    }
+
+    impl AgentFactoryWith<Walker, (u8, u8)> for WalkerFactory {
+        fn create_with(&self, agent_id: AgentId, position: (u8, u8)) -> Walker {
+            Walker {
+                id: agent_id,
+                position,
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_spawn_agents_with_their_spawn_arguments() {
+        let mut system: AgentSystem<Walker, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+        system = AgentSystem::new(0, Box::new(WalkerFactory), addr);
+
+        let walkers = system.spawn_swarm_with(&WalkerFactory, vec![(0, 0), (3, 4)]);
+        let walker = system.spawn_agent_with(&WalkerFactory, (7, 1));
+
+        assert_eq!(3, system.get_nb_agents());
+        assert_eq!((3, 4), system.kill_agent(walkers[1]).expect("Should kill the walker").position);
+        assert_eq!((7, 1), system.kill_agent(walker).expect("Should kill the walker").position);
+    }
+
+    #[test]
+    fn it_should_spawn_agents_requested_by_another_system() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut coordinator: AgentSystem<Person, Protocol> = AgentSystem::new(0, Box::new(PersonFactory), addr);
+        let mut worker: AgentSystem<Person, Protocol> = AgentSystem::new(1, Box::new(PersonFactory), addr);
+
+        coordinator.add_local_observer_system(1, worker.get_sender());
+        worker.add_local_observer_system(0, coordinator.get_sender());
+
+        let request_id = coordinator.request_remote_spawn(1, 3);
+
+        coordinator.run(());
+        // The worker spawns the agents on the first run and replies on the next one.
+        worker.run(());
+        worker.run(());
+        coordinator.run(());
+
+        let agents = coordinator.take_remote_spawn_reply(request_id).expect("Should receive the reply");
+
+        assert_eq!(3, worker.get_nb_agents());
+        assert_eq!(3, agents.len());
+        assert!(agents.iter().all(|&id| worker.is_alive(id)));
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Nomad {
+        id: AgentId,
+        nb_messages: usize,
+        nb_migrations: usize,
+        nb_errors: usize,
+        request: Option<Message<Protocol>>,
+    }
+
+    impl Agent for Nomad {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { false }
+
+        fn handle_message(&mut self, message: &Message<Self::C>) {
+            self.nb_messages += 1;
+            if message.reply_with.is_some() {
+                self.request = Some(message.clone());
+            }
+        }
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            self.request.take().map(|request| vec![request.create_reply(Performative::Agree, Protocol::Foo)])
+        }
+
+        fn on_migrate(&mut self, _: SystemId, _: SystemId) {
+            self.nb_migrations += 1;
+        }
+
+        fn on_error(&mut self, _: &AgentError) {
+            self.nb_errors += 1;
+        }
+    }
+
+    struct NomadFactory;
+
+    impl AgentFactory<Nomad> for NomadFactory {
+        fn create(&self, agent_id: AgentId) -> Nomad {
+            Nomad {
+                id: agent_id,
+                nb_messages: 0,
+                nb_migrations: 0,
+                nb_errors: 0,
+                request: None,
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_migrate_an_agent_and_forward_its_messages() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut origin: AgentSystem<Nomad, Protocol> = AgentSystem::new(0, Box::new(NomadFactory), addr);
+        let mut destination: AgentSystem<Nomad, Protocol> = AgentSystem::new(1, Box::new(NomadFactory), addr);
+
+        origin.add_local_observer_system(1, destination.get_sender());
+        destination.add_local_observer_system(0, origin.get_sender());
+        origin.enable_migration();
+        destination.enable_migration();
+
+        let nomad = origin.spawn_agent();
+        let message_to_nomad = || Message::inform(Recipient::Agent{ agent_id: nomad, system_id: 0 })
+            .priority(1)
+            .content(Protocol::Foo)
+            .build();
+
+        assert!(origin.migrate_agent(nomad, 1));
+        assert_eq!(0, origin.get_nb_agents());
+
+        // Received during the migration.
+        origin.get_sender().send(Packet::Agent(message_to_nomad())).expect("Should send the message");
+        origin.run(());
+        destination.run(());
+        destination.run(());
+        origin.run(());
+
+        // Received after the migration.
+        origin.get_sender().send(Packet::Agent(message_to_nomad())).expect("Should send the message");
+        origin.run(());
+        origin.run(());
+        destination.run(());
+
+        let (system_id, agent_id) = origin.get_migrated_address(nomad).expect("Should know the new address");
+
+        let nomad = destination.kill_agent(agent_id).expect("Should have the nomad");
+
+        assert_eq!(1, system_id);
+        assert_eq!(2, nomad.nb_messages);
+        assert_eq!(1, nomad.nb_migrations);
+    }
+
+    #[test]
+    fn it_should_let_a_migrated_agent_reply_to_a_message_received_before() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut origin: AgentSystem<Nomad, Protocol> = AgentSystem::new(0, Box::new(NomadFactory), addr);
+        let mut destination: AgentSystem<Nomad, Protocol> = AgentSystem::new(1, Box::new(NomadFactory), addr);
+
+        origin.add_local_observer_system(1, destination.get_sender());
+        destination.add_local_observer_system(0, origin.get_sender());
+        origin.enable_migration();
+        destination.enable_migration();
+
+        let nomad = origin.spawn_agent();
+        let requester = origin.spawn_agent();
+        let mut request = Message::request((0, nomad)).expect_reply().content(Protocol::Foo).build();
+        request.set_sender((0, requester));
+
+        origin.get_sender().send(Packet::Agent(request)).expect("Should send the message");
+        origin.run(());
+        assert!(origin.migrate_agent(nomad, 1));
+
+        origin.run(());
+        destination.run(());
+        destination.run(());
+        origin.run(());
+
+        let (_, agent_id) = origin.get_migrated_address(nomad).expect("Should know the new address");
+
+        assert_eq!(0, destination.kill_agent(agent_id).expect("Should have the nomad").nb_errors);
+        assert_eq!(1, origin.kill_agent(requester).expect("Should have the requester").nb_messages);
+    }
+
+    #[test]
+    fn it_should_bring_back_an_agent_the_destination_cant_receive() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut origin: AgentSystem<Nomad, Protocol> = AgentSystem::new(0, Box::new(NomadFactory), addr);
+        let mut destination: AgentSystem<Nomad, Protocol> = AgentSystem::new(1, Box::new(NomadFactory), addr);
+
+        origin.add_local_observer_system(1, destination.get_sender());
+        destination.add_local_observer_system(0, origin.get_sender());
+        origin.enable_migration();
+
+        let nomad = origin.spawn_agent();
+
+        assert!(origin.migrate_agent(nomad, 1));
+
+        origin.run(());
+        destination.run(());
+        destination.run(());
+        origin.run(());
+
+        let (system_id, agent_id) = origin.get_migrated_address(nomad).expect("Should know the new address");
+
+        assert_eq!(0, system_id);
+        assert_eq!(0, destination.get_nb_agents());
+        assert_eq!(1, origin.kill_agent(agent_id).expect("Should have the nomad").nb_errors);
+    }
+
+    #[test]
+    fn it_should_not_migrate_an_agent_when_the_migration_is_disabled() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut origin: AgentSystem<Nomad, Protocol> = AgentSystem::new(0, Box::new(NomadFactory), addr);
+
+        let nomad = origin.spawn_agent();
+
+        assert!(!origin.migrate_agent(nomad, 1));
+        assert!(origin.is_alive(nomad));
+    }
+
+    #[test]
+    fn it_should_bring_back_an_agent_whose_arrival_isnt_confirmed_in_time() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut origin: AgentSystem<Nomad, Protocol> = AgentSystem::new(0, Box::new(NomadFactory), addr);
+        let (sender, _receiver) = channel();
+
+        origin.add_local_observer_system(1, sender);
+        origin.enable_migration();
+        origin.set_migration_timeout(Duration::from_millis(0));
+
+        let nomad = origin.spawn_agent();
+
+        assert!(origin.migrate_agent(nomad, 1));
+        origin.run(());
+
+        let (system_id, agent_id) = origin.get_migrated_address(nomad).expect("Should know the new address");
+
+        assert_eq!(0, system_id);
+        assert_eq!(1, origin.kill_agent(agent_id).expect("Should have the nomad").nb_errors);
+    }
+
+    #[test]
+    fn it_should_remove_the_copy_of_an_agent_whose_arrival_is_confirmed_too_late() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut origin: AgentSystem<Nomad, Protocol> = AgentSystem::new(0, Box::new(NomadFactory), addr);
+        let mut destination: AgentSystem<Nomad, Protocol> = AgentSystem::new(1, Box::new(NomadFactory), addr);
+
+        origin.add_local_observer_system(1, destination.get_sender());
+        destination.add_local_observer_system(0, origin.get_sender());
+        origin.enable_migration();
+        destination.enable_migration();
+        origin.set_migration_timeout(Duration::from_millis(0));
+
+        let nomad = origin.spawn_agent();
+
+        assert!(origin.migrate_agent(nomad, 1));
+
+        // The migration times out before the destination receives the agent.
+        origin.run(());
+        destination.run(());
+        destination.run(());
+        assert_eq!(1, destination.get_nb_agents());
+
+        // The late acknowledgement cancels the migration.
+        origin.run(());
+        origin.run(());
+        destination.run(());
+
+        let (system_id, agent_id) = origin.get_migrated_address(nomad).expect("Should know the new address");
+
+        assert_eq!(0, system_id);
+        assert_eq!(0, destination.get_nb_agents());
+        assert!(origin.is_alive(agent_id));
+    }
+
+    #[test]
+    fn it_should_stop_forwarding_the_messages_of_a_migrated_agent_after_a_while() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut origin: AgentSystem<Nomad, Protocol> = AgentSystem::new(0, Box::new(NomadFactory), addr);
+        let (sender, _receiver) = channel();
+
+        origin.add_local_observer_system(1, sender);
+        origin.enable_migration();
+        origin.set_migration_timeout(Duration::from_millis(0));
+        origin.set_forwarding_duration(Duration::from_millis(0));
+
+        let nomad = origin.spawn_agent();
+
+        assert!(origin.migrate_agent(nomad, 1));
+        origin.run(());
+
+        assert_eq!(1, origin.get_nb_agents());
+        assert!(origin.get_migrated_address(nomad).is_none());
+    }
+
+    #[test]
+    fn it_should_kill_the_agents_matching_a_predicate() {
+        let mut pers_sys: AgentSystem<Person, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        pers_sys = AgentSystem::new(0, Box::new(PersonFactory), addr);
+
+        pers_sys.spawn_swarm(10);
+
+        assert_eq!(5, pers_sys.kill_where(|person| person.id.index % 2 == 0));
+        assert_eq!(5, pers_sys.get_nb_agents());
+    }
+
+    struct Responder {
+        id: AgentId,
+        forge_reply_to: Option<AgentId>,
+        request_to: Option<(AgentId, Timestamp)>,
+        replies: Vec<Message<Protocol>>,
+        received_in_reply_to: Vec<Option<Id>>,
+        errors: Vec<AgentError>,
+        conversations: ConversationTable,
+        conformance: Option<ConformanceChecker>,
+    }
+
+    impl Agent for Responder {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { false }
+
+        fn handle_message(&mut self, message: &Message<Self::C>) {
+            self.received_in_reply_to.push(message.in_reply_to);
+            if message.reply_with.is_some() {
+                self.replies.push(message.create_reply(Performative::Agree, Protocol::Foo));
+            }
+        }
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            if let Some(agent_id) = self.forge_reply_to.take() {
+                self.replies.push(Message::inform((0, agent_id)).in_reply_to(new_id()).content(Protocol::Foo).build());
+            }
+            if let Some((agent_id, deadline)) = self.request_to.take() {
+                self.replies.push(Message::request((0, agent_id)).reply_by(deadline).content(Protocol::Foo).build());
+            }
+            Some(self.replies.drain(..).collect())
+        }
+
+        fn on_error(&mut self, error: &AgentError) {
+            self.errors.push(error.clone());
+        }
+
+        fn conversations(&mut self) -> Option<&mut ConversationTable> {
+            Some(&mut self.conversations)
+        }
+
+        fn conformance(&mut self) -> Option<&mut ConformanceChecker> {
+            self.conformance.as_mut()
+        }
+    }
+
+    struct ResponderFactory;
+
+    impl AgentFactory<Responder> for ResponderFactory {
+        fn create(&self, agent_id: AgentId) -> Responder {
+            Responder {
+                id: agent_id,
+                forge_reply_to: None,
+                request_to: None,
+                replies: Vec::new(),
+                received_in_reply_to: Vec::new(),
+                errors: Vec::new(),
+                conversations: ConversationTable::new(),
+                conformance: None,
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_deliver_the_reply_to_a_received_message() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+
+        let requester = system.spawn_agent();
+        let responder = system.spawn_agent();
+        let mut request = Message::request((0, responder)).expect_reply().content(Protocol::Foo).build();
+        let reply_with = request.reply_with;
+        request.set_sender((0, requester));
+
+        system.get_sender().send(Packet::Agent(request)).expect("Should send the message");
+        system.run(());
+        system.run(());
+
+        let responder = system.kill_agent(responder).expect("Should keep the responder");
+        let requester = system.kill_agent(requester).expect("Should keep the requester");
+
+        assert!(responder.errors.is_empty());
+        assert_eq!(vec![reply_with], requester.received_in_reply_to);
+    }
+
+    #[test]
+    fn it_should_deliver_the_messages_by_priority() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+
+        let sender = system.spawn_agent();
+        let recipient = system.spawn_agent();
+        let references: Vec<Id> = (0..4).map(|_| new_id()).collect();
+        for (priority, &reference) in vec![0, 5, 0, 5].into_iter().zip(references.iter()) {
+            let mut message = Message::inform((0, recipient)).priority(priority).in_reply_to(reference).content(Protocol::Foo).build();
+            message.set_sender((0, sender));
+            system.get_sender().send(Packet::Agent(message)).expect("Should send the message");
+        }
+
+        system.run(());
+
+        let recipient = system.kill_agent(recipient).expect("Should keep the recipient");
+        let expected: Vec<Option<Id>> = vec![1, 3, 0, 2].into_iter().map(|i| Some(references[i])).collect();
+        assert_eq!(expected, recipient.received_in_reply_to);
+    }
+
+    #[test]
+    fn it_should_record_the_conversations_of_an_agent() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+
+        let requester = system.spawn_agent();
+        let responder = system.spawn_agent();
+        let mut request = Message::request((0, responder))
+            .protocol(InteractionProtocol::Request)
+            .start_conversation()
+            .expect_reply()
+            .content(Protocol::Foo)
+            .build();
+        let conversation_id = request.conversation_id.expect("Should start a conversation");
+        request.set_sender((0, requester));
+
+        system.get_sender().send(Packet::Agent(request)).expect("Should send the message");
+        system.run(());
+        system.run(());
+
+        let mut responder = system.kill_agent(responder).expect("Should keep the responder");
+        let conversation = responder.conversations.remove(conversation_id).expect("Should record the conversation");
+
+        assert_eq!(Some(InteractionProtocol::Request), conversation.protocol);
+        assert_eq!(vec![(0, requester)], conversation.participants);
+        assert_eq!(Performative::Agree, conversation.last_performative);
+        assert_eq!(ConversationState::Open, conversation.state);
+
+        let mut requester = system.kill_agent(requester).expect("Should keep the requester");
+        let conversation = requester.conversations.remove(conversation_id).expect("Should record the reply");
+
+        assert_eq!(vec![(0, responder.id)], conversation.participants);
+        assert_eq!(Performative::Agree, conversation.last_performative);
+    }
+
+    #[test]
+    fn it_should_drop_a_reply_to_a_message_the_agent_never_received() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+
+        let recipient = system.spawn_agent();
+        let forger = system.spawn_agent_with(&ForgerFactory, recipient);
+
+        system.run(());
+
+        assert_eq!(1, system.kill_agent(forger).expect("Should keep the forger").errors.len());
+        assert!(system.kill_agent(recipient).expect("Should keep the recipient").received_in_reply_to.is_empty());
+    }
+
+    #[test]
+    fn it_should_drop_a_reply_sent_after_the_reply_window() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+        system.set_reply_window(Duration::from_millis(0));
+
+        let requester = system.spawn_agent();
+        let responder = system.spawn_agent();
+        let mut request = Message::request((0, responder)).expect_reply().content(Protocol::Foo).build();
+        request.set_sender((0, requester));
+
+        system.get_sender().send(Packet::Agent(request)).expect("Should send the message");
+        system.run(());
+        system.run(());
+
+        let responder = system.kill_agent(responder).expect("Should keep the responder");
+        let requester = system.kill_agent(requester).expect("Should keep the requester");
+
+        assert_eq!(1, responder.errors.len());
+        assert!(requester.received_in_reply_to.is_empty());
+    }
+
+    #[test]
+    fn it_should_keep_the_reply_window_of_a_followed_conversation_open() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+        system.set_reply_window(Duration::from_millis(0));
+
+        let requester = system.spawn_agent();
+        let responder = system.spawn_agent();
+        let mut request = Message::request((0, responder)).start_conversation().expect_reply().content(Protocol::Foo).build();
+        request.set_sender((0, requester));
+
+        system.get_sender().send(Packet::Agent(request)).expect("Should send the message");
+        system.run(());
+        system.run(());
+
+        let responder = system.kill_agent(responder).expect("Should keep the responder");
+        let requester = system.kill_agent(requester).expect("Should keep the requester");
+
+        assert!(responder.errors.is_empty());
+        assert_eq!(1, requester.received_in_reply_to.len());
+    }
+
+    struct ForgerFactory;
+
+    impl AgentFactoryWith<Responder, AgentId> for ForgerFactory {
+        fn create_with(&self, agent_id: AgentId, recipient: AgentId) -> Responder {
+            let mut responder = ResponderFactory.create(agent_id);
+            responder.forge_reply_to = Some(recipient);
+            responder
+        }
+    }
+
+    struct RequesterFactory;
+
+    impl AgentFactoryWith<Responder, (AgentId, Timestamp)> for RequesterFactory {
+        fn create_with(&self, agent_id: AgentId, request_to: (AgentId, Timestamp)) -> Responder {
+            let mut responder = ResponderFactory.create(agent_id);
+            responder.request_to = Some(request_to);
+            responder
+        }
+    }
+
+    struct ConformingFactory;
+
+    impl AgentFactoryWith<Responder, ViolationPolicy> for ConformingFactory {
+        fn create_with(&self, agent_id: AgentId, policy: ViolationPolicy) -> Responder {
+            let mut responder = ResponderFactory.create(agent_id);
+            responder.conformance = Some(ConformanceChecker::new(policy));
+            responder
+        }
+    }
+
+    fn contract_net_message(performative: Performative, sender: AgentId, recipient: AgentId) -> Message<Protocol> {
+        let mut message = Message::builder(performative, (0, recipient))
+            .protocol(InteractionProtocol::ContractNet)
+            .start_conversation()
+            .expect_reply()
+            .content(Protocol::Foo)
+            .build();
+        message.set_sender((0, sender));
+        message
+    }
+
+    #[test]
+    fn it_should_drop_a_received_message_breaking_the_protocol() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+
+        let initiator = system.spawn_agent();
+        let participant = system.spawn_agent_with(&ConformingFactory, ViolationPolicy::Reject);
+        let accept = contract_net_message(Performative::AcceptProposal, initiator, participant);
+
+        system.get_sender().send(Packet::Agent(accept)).expect("Should send the message");
+        system.run(());
+
+        let mut participant = system.kill_agent(participant).expect("Should keep the participant");
+
+        assert!(participant.received_in_reply_to.is_empty());
+        assert_eq!(1, participant.conformance.take().expect("Should keep the checker").violations().len());
+    }
+
+    #[test]
+    fn it_should_drop_a_sent_message_breaking_the_protocol() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+
+        let initiator = system.spawn_agent();
+        let participant = system.spawn_agent_with(&ConformingFactory, ViolationPolicy::Reject);
+        let call = contract_net_message(Performative::CallForProposal, initiator, participant);
+
+        system.get_sender().send(Packet::Agent(call)).expect("Should send the message");
+        system.run(());
+        system.run(());
+
+        let participant = system.kill_agent(participant).expect("Should keep the participant");
+        let initiator = system.kill_agent(initiator).expect("Should keep the initiator");
+
+        assert_eq!(1, participant.received_in_reply_to.len());
+        assert!(participant.errors.iter().any(|error| matches!(*error, AgentError::ProtocolViolation { .. })));
+        assert!(initiator.received_in_reply_to.is_empty());
+    }
+
+    #[test]
+    fn it_should_stop_following_a_finished_conversation() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+
+        let initiator = system.spawn_agent();
+        let participant = system.spawn_agent_with(&ConformingFactory, ViolationPolicy::Reject);
+        let conversation_id = new_id();
+
+        for performative in vec![Performative::Request, Performative::Refuse] {
+            let mut message = Message::builder(performative, (0, participant))
+                .protocol(InteractionProtocol::Request)
+                .conversation(conversation_id)
+                .content(Protocol::Foo)
+                .build();
+            message.set_sender((0, initiator));
+            system.get_sender().send(Packet::Agent(message)).expect("Should send the message");
+        }
+        system.run(());
+
+        let mut participant = system.kill_agent(participant).expect("Should keep the participant");
+        let checker = participant.conformance.take().expect("Should keep the checker");
+
+        assert_eq!(2, participant.received_in_reply_to.len());
+        assert!(checker.violations().is_empty());
+        assert_eq!(0, checker.conversations().count());
+    }
+
+    #[test]
+    fn it_should_forget_a_request_replied_before_its_deadline() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+
+        let responder = system.spawn_agent();
+        let deadline = Timestamp::now() + Duration::from_secs(60);
+        let requester = system.spawn_agent_with(&RequesterFactory, (responder, deadline));
+
+        system.run(());
+        assert_eq!(1, system.get_nb_pending_replies());
+        system.run(());
+
+        assert_eq!(0, system.get_nb_pending_replies());
+        assert!(system.kill_agent(requester).expect("Should keep the requester").errors.is_empty());
+    }
+
+    #[test]
+    fn it_should_notify_the_sender_of_a_request_not_replied_before_its_deadline() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+
+        let silent = system.spawn_agent();
+        system.kill_agent(silent);
+        let requester = system.spawn_agent_with(&RequesterFactory, (silent, Timestamp(0)));
+
+        system.run(());
+
+        let requester = system.kill_agent(requester).expect("Should keep the requester");
+
+        assert_eq!(0, system.get_nb_pending_replies());
+        assert!(requester.errors.iter().any(|error| match *error {
+            AgentError::ReplyTimeout { .. } => true,
+            _ => false,
+        }));
+    }
+
+    struct Ponger {
+        id: AgentId,
+        behaviours: Behaviours<Protocol>,
+        received: Arc<Mutex<Vec<Performative>>>,
+    }
+
+    impl Agent for Ponger {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { false }
+
+        fn handle_message(&mut self, _: &Message<Self::C>) {}
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            panic!("The behaviours of the agent should run in place of act")
+        }
+
+        fn behaviours(&mut self) -> Option<&mut Behaviours<Self::C>> {
+            Some(&mut self.behaviours)
+        }
+
+        fn mailbox(&mut self) -> Option<&mut Mailbox<Self::C>> {
+            Some(self.behaviours.mailbox())
+        }
+    }
+
+    struct PongerFactory;
+
+    impl AgentFactory<Ponger> for PongerFactory {
+        fn create(&self, agent_id: AgentId) -> Ponger {
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let log = received.clone();
+            let mut behaviours = Behaviours::new();
+            behaviours.add(cyclic(move |context: &mut Context<Protocol>| {
+                while let Some(message) = context.receive() {
+                    log.lock().unwrap().push(message.performative.clone());
+                    if message.performative == Performative::Request {
+                        context.send(message.create_reply(Performative::Agree, Protocol::Foo));
+                    }
+                }
+            }));
+
+            Ponger { id: agent_id, behaviours, received }
+        }
+    }
+
+    #[test]
+    fn it_should_run_the_behaviours_of_an_agent_in_place_of_act() {
+        let mut system: AgentSystem<Ponger, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(PongerFactory), addr);
+
+        let pinger = system.spawn_agent();
+        let ponger = system.spawn_agent();
+        let mut request = Message::request((0, ponger)).expect_reply().content(Protocol::Foo).build();
+        request.set_sender((0, pinger));
+
+        system.get_sender().send(Packet::Agent(request)).expect("Should send the message");
+        system.run(());
+        system.run(());
+        system.run(());
+
+        let ponger = system.kill_agent(ponger).expect("Should keep the ponger, its act would panic");
+        let pinger = system.kill_agent(pinger).expect("Should keep the pinger");
+
+        assert_eq!(vec![Performative::Request], *ponger.received.lock().unwrap());
+        assert_eq!(vec![Performative::Agree], *pinger.received.lock().unwrap());
+    }
+
+    struct Picky {
+        id: AgentId,
+        mailbox: Mailbox<Protocol>,
+        received: Vec<Performative>,
+    }
+
+    impl Agent for Picky {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { false }
+
+        fn handle_message(&mut self, _: &Message<Self::C>) {
+            panic!("The messages should be queued in the mailbox of the agent")
+        }
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            let agreements = MessageTemplate::any().performative(Performative::Agree);
+            while let Some(message) = self.mailbox.receive(&agreements) {
+                self.received.push(message.performative);
+            }
+            None
+        }
+
+        fn mailbox(&mut self) -> Option<&mut Mailbox<Self::C>> {
+            Some(&mut self.mailbox)
+        }
+    }
+
+    struct PickyFactory;
+
+    impl AgentFactory<Picky> for PickyFactory {
+        fn create(&self, agent_id: AgentId) -> Picky {
+            Picky { id: agent_id, mailbox: Mailbox::new(), received: Vec::new() }
+        }
+    }
+
+    #[test]
+    fn it_should_keep_the_messages_an_agent_doesnt_receive_in_its_mailbox() {
+        let mut system: AgentSystem<Picky, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(PickyFactory), addr);
+
+        let sender = system.spawn_agent();
+        let picky = system.spawn_agent();
+        for performative in vec![Performative::Request, Performative::Agree] {
+            let mut message = Message::builder(performative, (0, picky)).content(Protocol::Foo).build();
+            message.set_sender((0, sender));
+            system.get_sender().send(Packet::Agent(message)).expect("Should send the message");
+        }
+
+        system.run(());
+        system.run(());
+
+        let picky = system.kill_agent(picky).expect("Should keep the agent, its handle_message would panic");
+
+        assert_eq!(vec![Performative::Agree], picky.received);
+        assert_eq!(1, picky.mailbox.len());
+    }
+
+    struct Sleeper {
+        id: AgentId,
+        timers: Timers<Protocol>,
+        nb_reminders: usize,
+    }
+
+    impl Agent for Sleeper {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { false }
+
+        fn on_start(&mut self) {
+            let reminder = Message::inform((0, self.id)).content(Protocol::Foo).build();
+            self.timers.schedule(Delay::Ticks(2), reminder.clone());
+            let cancelled = self.timers.schedule(Delay::Ticks(1), reminder);
+            self.timers.cancel(cancelled);
+        }
+
+        fn handle_message(&mut self, _: &Message<Self::C>) {
+            self.nb_reminders += 1;
+        }
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> { None }
+
+        fn timers(&mut self) -> Option<&mut Timers<Self::C>> {
+            Some(&mut self.timers)
+        }
+    }
+
+    struct SleeperFactory;
+
+    impl AgentFactory<Sleeper> for SleeperFactory {
+        fn create(&self, agent_id: AgentId) -> Sleeper {
+            Sleeper { id: agent_id, timers: Timers::new(), nb_reminders: 0 }
+        }
+    }
+
+    fn sleepers() -> AgentSystem<Sleeper, Protocol> {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        AgentSystem::new(0, Box::new(SleeperFactory), addr)
+    }
+
+    #[test]
+    fn it_should_deliver_the_reminder_an_agent_scheduled() {
+        let mut system = sleepers();
+        let sleeper = system.spawn_agent();
+
+        system.run(());
+        assert_eq!(1, system.get_nb_timers());
+        system.run(());
+        assert_eq!(1, system.get_nb_timers());
+        system.run(());
+
+        assert_eq!(0, system.get_nb_timers());
+        assert_eq!(1, system.kill_agent(sleeper).expect("Should keep the sleeper").nb_reminders);
+    }
+
+    #[test]
+    fn it_should_cancel_a_message_scheduled_by_the_system() {
+        let mut system = sleepers();
+        let sleeper = system.spawn_agent();
+        let message = Message::inform((0, sleeper)).content(Protocol::Foo).build();
+
+        let timer = system.schedule(sleeper, Delay::Ticks(1), message.clone());
+        system.schedule(sleeper, Delay::At(Timestamp(0)), message);
+        assert!(system.cancel_timer(timer));
+        assert!(!system.cancel_timer(timer));
+
+        system.run(());
+
+        assert_eq!(1, system.get_nb_timers());
+        assert_eq!(1, system.kill_agent(sleeper).expect("Should keep the sleeper").nb_reminders);
+    }
+
+    #[test]
+    fn it_should_restore_the_pending_timers_of_a_checkpoint() {
+        let mut system = sleepers();
+        system.spawn_agent();
+        system.run(());
+        let checkpoint = system.checkpoint_timers().expect("Should save the timers");
+
+        let mut restored: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        restored = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+        let responder = restored.spawn_agent();
+
+        assert_eq!(1, restored.restore_timers(&checkpoint).expect("Should restore the timers"));
+        assert!(restored.restore_timers(&[1, 2, 3]).is_err());
+
+        restored.run(());
+        assert_eq!(1, restored.get_nb_timers());
+        restored.run(());
+
+        assert_eq!(0, restored.get_nb_timers());
+        assert_eq!(1, restored.kill_agent(responder).expect("Should keep the responder").received_in_reply_to.len());
+    }
+
+    #[test]
+    fn it_should_not_restore_the_timers_of_the_agents_gone() {
+        let mut system = sleepers();
+        let sleeper = system.spawn_agent();
+        system.run(());
+        let checkpoint = system.checkpoint_timers().expect("Should save the timers");
+
+        let mut restored = sleepers();
+        let (sender, receiver) = channel();
+        restored.set_dead_letters_sink(sender);
+
+        assert_eq!(0, restored.restore_timers(&checkpoint).expect("Should restore the timers"));
+        assert_eq!(0, restored.get_nb_timers());
+        assert_eq!((0, sleeper), receiver.try_recv().expect("Should send the timer to the dead letters").sender);
+    }
+
+    #[test]
+    fn it_should_restore_a_checkpoint_once() {
+        let mut system = sleepers();
+        system.spawn_agent();
+        system.run(());
+        let checkpoint = system.checkpoint_timers().expect("Should save the timers");
+
+        system.restore_timers(&checkpoint).expect("Should restore the timers");
+        system.restore_timers(&checkpoint).expect("Should restore the timers");
+
+        assert_eq!(1, system.get_nb_timers());
+    }
+
+    #[test]
+    fn it_should_check_a_scheduled_message_when_it_fires() {
+        let mut system: AgentSystem<Responder, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(ResponderFactory), addr);
+
+        let sender = system.spawn_agent();
+        let recipient = system.spawn_agent();
+        let forged = Message::inform((0, recipient)).in_reply_to(new_id()).content(Protocol::Foo).build();
+        system.schedule(sender, Delay::Ticks(1), forged);
+
+        system.run(());
+        system.run(());
+
+        assert_eq!(1, system.kill_agent(sender).expect("Should keep the sender").errors.len());
+        assert!(system.kill_agent(recipient).expect("Should keep the recipient").received_in_reply_to.is_empty());
+    }
+
+    struct Newsroom {
+        id: AgentId,
+        accept_subscriptions: bool,
+        nb_news: usize,
+        received: Vec<Performative>,
+        conversations: ConversationTable,
+    }
+
+    impl Agent for Newsroom {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { false }
+
+        fn handle_message(&mut self, message: &Message<Self::C>) {
+            self.received.push(message.performative.clone());
+        }
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            None
+        }
+
+        fn accept_subscription(&mut self, _: &Message<Self::C>) -> Option<String> {
+            if self.accept_subscriptions { Some("weather".to_string()) } else { None }
+        }
+
+        fn notifications(&mut self) -> Vec<(String, Self::C)> {
+            let nb_news = self.nb_news;
+            self.nb_news = 0;
+            (0..nb_news).map(|_| ("weather".to_string(), Protocol::Foo)).collect()
+        }
+
+        fn conversations(&mut self) -> Option<&mut ConversationTable> {
+            Some(&mut self.conversations)
+        }
+    }
+
+    struct NewsroomFactory(bool);
+
+    impl AgentFactory<Newsroom> for NewsroomFactory {
+        fn create(&self, agent_id: AgentId) -> Newsroom {
+            Newsroom {
+                id: agent_id,
+                accept_subscriptions: self.0,
+                nb_news: 0,
+                received: Vec::new(),
+                conversations: ConversationTable::new(),
+            }
+        }
+    }
+
+    fn subscription(system: &AgentSystem<Newsroom, Protocol>, subscriber: AgentId, publisher: AgentId) -> Message<Protocol> {
+        let mut subscribe = Message::subscribe((0, publisher))
+            .start_conversation()
+            .expect_reply()
+            .content(Protocol::Foo)
+            .build();
+        subscribe.set_sender((0, subscriber));
+        system.get_sender().send(Packet::Agent(subscribe.clone())).expect("Should send the message");
+        subscribe
+    }
+
+    #[test]
+    fn it_should_notify_the_subscribers_of_a_topic() {
+        let mut system: AgentSystem<Newsroom, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(NewsroomFactory(true)), addr);
+
+        let publisher = system.spawn_agent();
+        let subscribers = system.spawn_swarm(2);
+        for &subscriber in subscribers.iter() {
+            subscription(&system, subscriber, publisher);
+        }
+
+        system.run(());
+        assert_eq!(2, system.get_nb_subscriptions(publisher));
+
+        assert_eq!(2, system.notify_subscribers(publisher, "weather", Protocol::Foo));
+        assert_eq!(0, system.notify_subscribers(publisher, "sport", Protocol::Foo));
+        system.run(());
+
+        for subscriber in subscribers {
+            let subscriber = system.kill_agent(subscriber).expect("Should keep the subscriber");
+            assert_eq!(vec![Performative::Agree, Performative::Inform], subscriber.received);
+        }
+    }
+
+    #[test]
+    fn it_should_send_the_notifications_of_an_agent() {
+        let mut system: AgentSystem<Newsroom, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(NewsroomFactory(true)), addr);
+
+        let publisher = system.spawn_agent();
+        let subscriber = system.spawn_agent();
+        subscription(&system, subscriber, publisher);
+        system.run(());
+
+        if let Some(publisher) = system.agents.get_mut(publisher.index) {
+            publisher.nb_news = 1;
+        }
+        system.run(());
+
+        let subscriber = system.kill_agent(subscriber).expect("Should keep the subscriber");
+        assert_eq!(vec![Performative::Agree, Performative::Inform], subscriber.received);
+    }
+
+    #[test]
+    fn it_should_refuse_a_subscription_the_agent_doesnt_accept() {
+        let mut system: AgentSystem<Newsroom, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(NewsroomFactory(false)), addr);
+
+        let publisher = system.spawn_agent();
+        let subscriber = system.spawn_agent();
+        subscription(&system, subscriber, publisher);
+        system.run(());
+        system.run(());
+
+        assert_eq!(0, system.get_nb_subscriptions(publisher));
+        assert_eq!(vec![Performative::Refuse], system.kill_agent(subscriber).expect("Should keep the subscriber").received);
+    }
+
+    #[test]
+    fn it_should_cancel_a_subscription() {
+        let mut system: AgentSystem<Newsroom, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(NewsroomFactory(true)), addr);
+
+        let publisher = system.spawn_agent();
+        let subscriber = system.spawn_agent();
+        let subscribe = subscription(&system, subscriber, publisher);
+        system.run(());
+
+        let mut cancel = Message::builder(Performative::Cancel, (0, publisher))
+            .conversation(subscribe.conversation_id.expect("Should start a conversation"))
+            .content(Protocol::Foo)
+            .build();
+        cancel.set_sender((0, subscriber));
+        system.get_sender().send(Packet::Agent(cancel)).expect("Should send the message");
+        system.run(());
+
+        assert_eq!(0, system.get_nb_subscriptions(publisher));
+    }
+
+    #[test]
+    fn it_should_record_the_answers_to_a_subscription_in_the_conversation_of_the_publisher() {
+        let mut system: AgentSystem<Newsroom, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(NewsroomFactory(true)), addr);
+
+        let publisher = system.spawn_agent();
+        let subscriber = system.spawn_agent();
+        let subscribe = subscription(&system, subscriber, publisher);
+        let conversation_id = subscribe.conversation_id.expect("Should start a conversation");
+        system.run(());
+
+        let mut newsroom = system.kill_agent(publisher).expect("Should keep the publisher");
+        let conversation = newsroom.conversations.remove(conversation_id).expect("Should record the subscription");
+        assert_eq!(Performative::Agree, conversation.last_performative);
+    }
+
+    #[test]
+    fn it_should_take_and_cancel_the_subscriptions_sent_to_every_agent() {
+        let mut system: AgentSystem<Newsroom, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(NewsroomFactory(true)), addr);
+
+        let subscriber = system.spawn_agent();
+        let publishers = system.spawn_swarm(2);
+        let mut subscribe = Message::subscribe(Recipient::Broadcast{ system_id: Some(0) })
+            .start_conversation()
+            .content(Protocol::Foo)
+            .build();
+        subscribe.set_sender((0, subscriber));
+        system.get_sender().send(Packet::Agent(subscribe)).expect("Should send the message");
+        system.run(());
+
+        assert!(publishers.iter().all(|&publisher| system.get_nb_subscriptions(publisher) == 1));
+
+        let mut cancel = Message::builder(Performative::Cancel, Recipient::Broadcast{ system_id: Some(0) })
+            .content(Protocol::Foo)
+            .build();
+        cancel.set_sender((0, subscriber));
+        system.get_sender().send(Packet::Agent(cancel)).expect("Should send the message");
+        system.run(());
+
+        assert!(publishers.iter().all(|&publisher| system.get_nb_subscriptions(publisher) == 0));
+        assert_eq!(vec![Performative::Agree, Performative::Agree], system.kill_agent(subscriber).expect("Should keep the subscriber").received);
+    }
+
+    #[test]
+    fn it_should_forget_the_dead_subscribers() {
+        let mut system: AgentSystem<Newsroom, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        system = AgentSystem::new(0, Box::new(NewsroomFactory(true)), addr);
+
+        let publisher = system.spawn_agent();
+        let subscribers = system.spawn_swarm(2);
+        for &subscriber in subscribers.iter() {
+            subscription(&system, subscriber, publisher);
+        }
+        system.run(());
+
+        system.kill_agent(subscribers[0]);
+
+        assert_eq!(1, system.get_nb_subscriptions(publisher));
+        assert_eq!(1, system.notify_subscribers(publisher, "weather", Protocol::Foo));
+    }
+
+    struct Buyer {
+        id: AgentId,
+    }
+
+    impl Agent for Buyer {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { false }
+
+        fn handle_message(&mut self, _: &Message<Self::C>) {}
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            Some(vec! [
+                Message::call_for_proposal(Recipient::Broadcast{ system_id: Some(0) })
+                    .priority(1)
+                    .content(Protocol::Foo)
+                    .build()
+            ])
+        }
+    }
+
+    struct BuyerFactory;
+
+    impl AgentFactory<Buyer> for BuyerFactory {
+        fn create(&self, agent_id: AgentId) -> Buyer {
+            Buyer { id: agent_id }
+        }
+    }
+
+    struct Seller {
+        id: AgentId,
+        calls_for_proposal: Sender<AgentId>,
+    }
+
+    impl Agent for Seller {
+        type C = Protocol;
+
+        fn id(&self) -> AgentId { self.id }
+
+        fn set_id(&mut self, id: AgentId) { self.id = id }
+
+        fn is_dead(&self) -> bool { false }
+
+        fn handle_message(&mut self, message: &Message<Self::C>) {
+            if message.performative == Performative::CallForProposal {
+                self.calls_for_proposal.send(message.sender.1).expect("Should notify the call for proposal");
+            }
+        }
+
+        fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
+            None
+        }
+    }
+
+    struct SellerFactory(Sender<AgentId>);
+
+    impl AgentFactory<Seller> for SellerFactory {
+        fn create(&self, agent_id: AgentId) -> Seller {
+            Seller {
+                id: agent_id,
+                calls_for_proposal: self.0.clone(),
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_host_agents_of_different_types_spawned_by_name() {
+        let (sender, calls_for_proposal) = channel();
+        let mut market: AgentSystem<BoxedAgent<Protocol>, Protocol>;
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+        market = AgentSystem::new(0, Box::new(BoxedAgentFactory::new(BuyerFactory)), addr);
+        market.register_factory("buyer", Box::new(BoxedAgentFactory::new(BuyerFactory)));
+        market.register_factory("seller", Box::new(BoxedAgentFactory::new(SellerFactory(sender))));
+
+        let buyer = market.spawn_agent_of("buyer").expect("Should spawn a buyer");
+        market.spawn_swarm_of("seller", 2).expect("Should spawn the sellers");
+
+        assert!(market.spawn_agent_of("broker").is_none());
+        assert_eq!(3, market.get_nb_agents());
+
+        market.run(());
+
+        assert_eq!(vec![buyer, buyer], calls_for_proposal.try_iter().collect::<Vec<_>>());
     }
 
     #[derive(Serialize, Deserialize, Clone, Debug)]
     struct AgentTestMsg {
-        id: usize,
-        id_other_agent: usize,
+        id: AgentId,
+        id_other_agent: AgentId,
     }
 
     impl Content for AgentTestMsg {}
 
     #[derive(Serialize, Deserialize, Clone, Debug)]
     enum ProtocolGreeting {
-        Greeting(usize),
+        Greeting(AgentId),
     }
 
     impl Content for ProtocolGreeting {}
@@ -254,9 +3186,9 @@ mod test_sytem {
     impl Agent for AgentTestMsg {
         type C = ProtocolGreeting;
 
-        fn id(&self) -> usize { self.id }
+        fn id(&self) -> AgentId { self.id }
 
-        fn set_id(&mut self, id: usize) { self.id = id }
+        fn set_id(&mut self, id: AgentId) { self.id = id }
 
         fn is_dead(&self) -> bool { false }
 
@@ -270,17 +3202,10 @@ mod test_sytem {
 
         fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
             Some(vec! [
-                Message::new(
-                    Performative::Inform,
-                    Recipient::Agent{ agent_id: 1 - self.id(), system_id: 0 },
-                    0,
-                    1,
-                    None,
-                    None,
-                    None,
-                    None,
-                    ProtocolGreeting::Greeting(self.id()),
-                )
+                Message::inform(Recipient::Agent{ agent_id: AgentId::new(1 - self.id().index, 0), system_id: 0 })
+                    .priority(1)
+                    .content(ProtocolGreeting::Greeting(self.id()))
+                    .build()
             ])
         }
     }
@@ -288,10 +3213,10 @@ mod test_sytem {
     struct AgentTestMsgFactory;
 
     impl AgentFactory<AgentTestMsg> for AgentTestMsgFactory {
-        fn create(&self, agent_id: usize) -> AgentTestMsg {
+        fn create(&self, agent_id: AgentId) -> AgentTestMsg {
             AgentTestMsg {
                 id: agent_id,
-                id_other_agent: 0,
+                id_other_agent: AgentId::default(),
             }
         }
     }
@@ -315,7 +3240,7 @@ mod test_sytem {
 
     #[derive(Serialize, Deserialize, Clone)]
     struct AgentTestMsgBroadcast {
-        id: usize,
+        id: AgentId,
     }
 
     impl Content for AgentTestMsgBroadcast{}
@@ -323,9 +3248,9 @@ mod test_sytem {
     impl Agent for AgentTestMsgBroadcast {
         type C = ProtocolGreeting;
 
-        fn id(&self) -> usize { self.id }
+        fn id(&self) -> AgentId { self.id }
 
-        fn set_id(&mut self, id: usize) { self.id = id }
+        fn set_id(&mut self, id: AgentId) { self.id = id }
 
         fn is_dead(&self) -> bool { false }
 
@@ -339,17 +3264,10 @@ mod test_sytem {
 
         fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
             Some(vec! [
-                Message::new(
-                    Performative::Inform,
-                    Recipient::Broadcast{ system_id: None },
-                    0,
-                    1,
-                    None,
-                    None,
-                    None,
-                    None,
-                    ProtocolGreeting::Greeting(self.id()),
-                )
+                Message::inform(Recipient::Broadcast{ system_id: None })
+                    .priority(1)
+                    .content(ProtocolGreeting::Greeting(self.id()))
+                    .build()
             ])
         }
     }
@@ -357,7 +3275,7 @@ mod test_sytem {
     struct GentTestMsgBroadcastFactory;
 
     impl AgentFactory<AgentTestMsgBroadcast> for GentTestMsgBroadcastFactory {
-        fn create(&self, agent_id: usize) -> AgentTestMsgBroadcast {
+        fn create(&self, agent_id: AgentId) -> AgentTestMsgBroadcast {
             AgentTestMsgBroadcast {
                 id: agent_id,
             }
@@ -382,7 +3300,7 @@ mod test_sytem {
 
     #[derive(Serialize, Deserialize, Clone)]
     struct AgentTestMsgBetweenSystem {
-        id: usize,
+        id: AgentId,
         pos: (u8, u8),
         id_other_sytem: u8,
     }
@@ -399,9 +3317,9 @@ mod test_sytem {
     impl Agent for AgentTestMsgBetweenSystem {
         type C = ProtocolPos;
 
-        fn id(&self) -> usize { self.id }
+        fn id(&self) -> AgentId { self.id }
 
-        fn set_id(&mut self, id: usize) { self.id = id }
+        fn set_id(&mut self, id: AgentId) { self.id = id }
 
         fn is_dead(&self) -> bool { false }
 
@@ -415,17 +3333,10 @@ mod test_sytem {
 
         fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
             Some(vec! [
-                Message::new(
-                    Performative::Confirm,
-                    Recipient::Agent{ agent_id: 0, system_id: self.id_other_sytem },
-                    0,
-                    1,
-                    None,
-                    None,
-                    None,
-                    None,
-                    ProtocolPos::Position(self.pos.0, self.pos.1),
-                )
+                Message::builder(Performative::Confirm, Recipient::Agent{ agent_id: AgentId::new(0, 0), system_id: self.id_other_sytem })
+                    .priority(1)
+                    .content(ProtocolPos::Position(self.pos.0, self.pos.1))
+                    .build()
             ])
         }
     }
@@ -433,7 +3344,7 @@ mod test_sytem {
     struct AgentTestMsgBetweenSystemFactory(u8);
 
     impl AgentFactory<AgentTestMsgBetweenSystem> for AgentTestMsgBetweenSystemFactory {
-        fn create(&self, agent_id: usize) -> AgentTestMsgBetweenSystem {
+        fn create(&self, agent_id: AgentId) -> AgentTestMsgBetweenSystem {
             AgentTestMsgBetweenSystem {
                 id: agent_id,
                 pos: (0, 0),
@@ -0,0 +1,461 @@
+use std::collections::{HashSet, VecDeque};
+use std::collections::hash_set;
+use std::hash::Hash;
+
+use message::{Content, Message, Performative};
+
+/// Propositions the agent holds true.
+pub struct BeliefBase<C> {
+    beliefs: HashSet<C>,
+}
+
+impl<C: Content + Eq + Hash> BeliefBase<C> {
+    pub fn new() -> Self {
+        BeliefBase { beliefs: HashSet::new() }
+    }
+
+    pub fn believe(&mut self, belief: C) {
+        self.beliefs.insert(belief);
+    }
+
+    /// Stop believing the proposition, returns false if it wasn't believed.
+    pub fn forget(&mut self, belief: &C) -> bool {
+        self.beliefs.remove(belief)
+    }
+
+    pub fn believes(&self, belief: &C) -> bool {
+        self.beliefs.contains(belief)
+    }
+
+    pub fn iter(&self) -> hash_set::Iter<'_, C> {
+        self.beliefs.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.beliefs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.beliefs.is_empty()
+    }
+
+    /// Believe the content of an `Inform` or a `Confirm`, and forget the one of a `Disconfirm`.
+    /// Returns false if the message doesn't change the beliefs.
+    pub fn update(&mut self, message: &Message<C>) -> bool {
+        match message.performative {
+            Performative::Inform | Performative::Confirm => self.beliefs.insert(message.content.clone()),
+            Performative::Disconfirm => self.beliefs.remove(&message.content),
+            _ => false,
+        }
+    }
+}
+
+impl<C: Content + Eq + Hash> Default for BeliefBase<C> {
+    fn default() -> Self {
+        BeliefBase::new()
+    }
+}
+
+/// What the reasoner does once a step of a plan has run.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Outcome<G> {
+    /// Run the next step of the plan in the next tick.
+    Next,
+    /// Run the same step again in the next tick.
+    Wait,
+    /// Pursue the subgoal on top of the plan, which resumes at its next step once the subgoal is achieved.
+    Subgoal(G),
+    /// Give up the plan, the goal is pursued with another applicable plan if any.
+    Fail,
+}
+
+/// What a step of a plan sees of its agent.
+pub struct Deliberation<'a, C: 'a, G: 'a> {
+    goal: &'a G,
+    beliefs: &'a mut BeliefBase<C>,
+    goals: &'a mut VecDeque<G>,
+    outbox: &'a mut Vec<Message<C>>,
+}
+
+impl<'a, C: Content + Eq + Hash, G> Deliberation<'a, C, G> {
+    /// Goal the plan is pursuing.
+    pub fn goal(&self) -> &G {
+        self.goal
+    }
+
+    pub fn beliefs(&self) -> &BeliefBase<C> {
+        self.beliefs
+    }
+
+    pub fn believe(&mut self, belief: C) {
+        self.beliefs.believe(belief);
+    }
+
+    pub fn forget(&mut self, belief: &C) -> bool {
+        self.beliefs.forget(belief)
+    }
+
+    /// Adopt a new goal, pursued once the current intentions are over.
+    pub fn adopt(&mut self, goal: G) {
+        self.goals.push_back(goal);
+    }
+
+    pub fn send(&mut self, message: Message<C>) {
+        self.outbox.push(message);
+    }
+}
+
+type Condition<C> = Box<dyn Fn(&BeliefBase<C>) -> bool + Send>;
+type Body<C, G> = Box<dyn Fn(&mut Deliberation<C, G>) -> Outcome<G> + Send>;
+
+/// Recipe to achieve a goal, applicable when its context condition holds on the beliefs.
+pub struct Plan<C, G> {
+    name: String,
+    trigger: G,
+    context: Condition<C>,
+    body: Vec<Body<C, G>>,
+}
+
+impl<C: Content + Eq + Hash, G> Plan<C, G> {
+    pub fn new(name: &str, trigger: G) -> Self {
+        Plan {
+            name: name.to_string(),
+            trigger,
+            context: Box::new(|_| true),
+            body: Vec::new(),
+        }
+    }
+
+    /// Condition on the beliefs for the plan to be selected.
+    pub fn context<F>(mut self, context: F) -> Self where F: Fn(&BeliefBase<C>) -> bool + Send + 'static {
+        self.context = Box::new(context);
+        self
+    }
+
+    /// Add a step to the body of the plan, steps run one per tick.
+    pub fn step<F>(mut self, step: F) -> Self where F: Fn(&mut Deliberation<C, G>) -> Outcome<G> + Send + 'static {
+        self.body.push(Box::new(step));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Plan adopted to achieve a goal, with the next step to run.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Intention<G> {
+    pub goal: G,
+    pub plan: usize,
+    pub step: usize,
+    tried: Vec<usize>,
+}
+
+/// BDI reasoner of an agent: the agent passes its messages to `handle_message` to update the beliefs,
+/// and calls `step` from `act` to run one step of its current intention.
+pub struct Reasoner<C, G> {
+    beliefs: BeliefBase<C>,
+    goals: VecDeque<G>,
+    plans: Vec<Plan<C, G>>,
+    intentions: Vec<Intention<G>>,
+    achieved: Vec<G>,
+    failed: Vec<G>,
+}
+
+impl<C: Content + Eq + Hash, G: Clone + PartialEq> Reasoner<C, G> {
+    pub fn new() -> Self {
+        Reasoner {
+            beliefs: BeliefBase::new(),
+            goals: VecDeque::new(),
+            plans: Vec::new(),
+            intentions: Vec::new(),
+            achieved: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+
+    pub fn add_plan(&mut self, plan: Plan<C, G>) {
+        self.plans.push(plan);
+    }
+
+    /// Adopt a goal, pursued once the goals adopted before are over.
+    pub fn adopt(&mut self, goal: G) {
+        self.goals.push_back(goal);
+    }
+
+    pub fn beliefs(&self) -> &BeliefBase<C> {
+        &self.beliefs
+    }
+
+    pub fn beliefs_mut(&mut self) -> &mut BeliefBase<C> {
+        &mut self.beliefs
+    }
+
+    /// Update the beliefs from the message, see `BeliefBase::update`.
+    pub fn handle_message(&mut self, message: &Message<C>) -> bool {
+        self.beliefs.update(message)
+    }
+
+    /// Intention stack, the current intention last.
+    pub fn intentions(&self) -> &[Intention<G>] {
+        &self.intentions
+    }
+
+    /// Name of the plan of the current intention.
+    pub fn current_plan(&self) -> Option<&str> {
+        self.intentions.last().map(|intention| self.plans[intention.plan].name())
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.intentions.is_empty() && self.goals.is_empty()
+    }
+
+    /// Top level goals achieved since the last call.
+    pub fn take_achieved(&mut self) -> Vec<G> {
+        self.achieved.drain(..).collect()
+    }
+
+    /// Top level goals without any plan left to achieve them since the last call.
+    pub fn take_failed(&mut self) -> Vec<G> {
+        self.failed.drain(..).collect()
+    }
+
+    /// Run one step of the current intention, adopting the next goal if there is none.
+    /// Returns the messages sent by the step.
+    pub fn step(&mut self) -> Vec<Message<C>> {
+        let mut outbox = Vec::new();
+
+        if self.intentions.is_empty() {
+            match self.goals.pop_front() {
+                Some(goal) => if !self.intend(goal.clone(), Vec::new()) {
+                    warn!("No plan is applicable to a goal, it's dropped");
+                    self.failed.push(goal);
+                    return outbox;
+                },
+                None => return outbox,
+            }
+        }
+
+        let (plan, step, goal) = match self.intentions.last() {
+            Some(intention) => (intention.plan, intention.step, intention.goal.clone()),
+            None => return outbox,
+        };
+
+        let outcome = match self.plans[plan].body.get(step) {
+            Some(body) => {
+                let mut deliberation = Deliberation {
+                    goal: &goal,
+                    beliefs: &mut self.beliefs,
+                    goals: &mut self.goals,
+                    outbox: &mut outbox,
+                };
+                body(&mut deliberation)
+            },
+            None => Outcome::Next,
+        };
+
+        match outcome {
+            Outcome::Next => self.advance(),
+            Outcome::Wait => {},
+            Outcome::Subgoal(subgoal) => if !self.intend(subgoal, Vec::new()) {
+                self.fail();
+            },
+            Outcome::Fail => self.fail(),
+        }
+
+        outbox
+    }
+
+    /// Push an intention to achieve the goal with the first applicable plan not tried yet.
+    fn intend(&mut self, goal: G, mut tried: Vec<usize>) -> bool {
+        let plan = self.plans.iter().enumerate()
+            .position(|(index, plan)| plan.trigger == goal && !tried.contains(&index) && (plan.context)(&self.beliefs));
+
+        match plan {
+            Some(plan) => {
+                tried.push(plan);
+                self.intentions.push(Intention { goal, plan, step: 0, tried });
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Move the current intention to its next step, and pop the intentions whose plan is over.
+    fn advance(&mut self) {
+        while let Some(mut intention) = self.intentions.pop() {
+            intention.step += 1;
+            if intention.step < self.plans[intention.plan].body.len() {
+                self.intentions.push(intention);
+                return;
+            }
+
+            if self.intentions.is_empty() {
+                self.achieved.push(intention.goal);
+            }
+        }
+    }
+
+    /// Give up the plan of the current intention, and try another one for its goal. Without any,
+    /// the goal fails and so does the plan of the intention below.
+    fn fail(&mut self) {
+        while let Some(intention) = self.intentions.pop() {
+            if self.intend(intention.goal.clone(), intention.tried) {
+                return;
+            }
+
+            if self.intentions.is_empty() {
+                warn!("No plan is left to achieve a goal, it's dropped");
+                self.failed.push(intention.goal);
+            }
+        }
+    }
+}
+
+impl<C: Content + Eq + Hash, G: Clone + PartialEq> Default for Reasoner<C, G> {
+    fn default() -> Self {
+        Reasoner::new()
+    }
+}
+
+#[cfg(test)]
+mod test_bdi {
+
+    use super::*;
+    use agent::AgentId;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+    enum Fact {
+        DoorOpen,
+        HasKey,
+        InRoom,
+    }
+
+    impl Content for Fact {}
+
+    #[derive(Clone, PartialEq, Debug)]
+    enum Goal {
+        EnterRoom,
+        OpenDoor,
+    }
+
+    fn message(performative: Performative, fact: Fact) -> Message<Fact> {
+        Message::builder(performative, (0, AgentId::new(0, 0))).content(fact).build()
+    }
+
+    fn run(reasoner: &mut Reasoner<Fact, Goal>, ticks: usize) -> Vec<Message<Fact>> {
+        (0..ticks).flat_map(|_| reasoner.step()).collect()
+    }
+
+    fn reasoner() -> Reasoner<Fact, Goal> {
+        let mut reasoner = Reasoner::new();
+        reasoner.add_plan(Plan::new("walk in", Goal::EnterRoom)
+            .context(|beliefs| beliefs.believes(&Fact::DoorOpen))
+            .step(|deliberation| {
+                deliberation.believe(Fact::InRoom);
+                Outcome::Next
+            }));
+        reasoner.add_plan(Plan::new("open then walk in", Goal::EnterRoom)
+            .step(|_| Outcome::Subgoal(Goal::OpenDoor))
+            .step(|deliberation| {
+                deliberation.believe(Fact::InRoom);
+                Outcome::Next
+            }));
+        reasoner.add_plan(Plan::new("unlock", Goal::OpenDoor)
+            .context(|beliefs| beliefs.believes(&Fact::HasKey))
+            .step(|deliberation| {
+                deliberation.send(message(Performative::Inform, Fact::DoorOpen));
+                deliberation.believe(Fact::DoorOpen);
+                Outcome::Next
+            }));
+        reasoner
+    }
+
+    #[test]
+    fn it_should_update_the_beliefs_from_the_messages() {
+        let mut beliefs = BeliefBase::new();
+
+        assert!(beliefs.update(&message(Performative::Inform, Fact::DoorOpen)));
+        assert!(beliefs.update(&message(Performative::Confirm, Fact::HasKey)));
+        assert!(!beliefs.update(&message(Performative::Request, Fact::InRoom)));
+        assert!(beliefs.update(&message(Performative::Disconfirm, Fact::DoorOpen)));
+
+        assert!(beliefs.believes(&Fact::HasKey));
+        assert!(!beliefs.believes(&Fact::DoorOpen));
+        assert_eq!(1, beliefs.len());
+    }
+
+    #[test]
+    fn it_should_select_the_first_plan_whose_context_holds() {
+        let mut reasoner = reasoner();
+        reasoner.handle_message(&message(Performative::Inform, Fact::DoorOpen));
+        reasoner.adopt(Goal::EnterRoom);
+
+        reasoner.step();
+
+        assert!(reasoner.beliefs().believes(&Fact::InRoom));
+        assert_eq!(vec![Goal::EnterRoom], reasoner.take_achieved());
+        assert!(reasoner.is_idle());
+    }
+
+    #[test]
+    fn it_should_pursue_a_subgoal_on_the_intention_stack() {
+        let mut reasoner = reasoner();
+        reasoner.beliefs_mut().believe(Fact::HasKey);
+        reasoner.adopt(Goal::EnterRoom);
+
+        reasoner.step();
+        assert_eq!(vec![Goal::EnterRoom, Goal::OpenDoor], reasoner.intentions().iter().map(|i| i.goal.clone()).collect::<Vec<_>>());
+        assert_eq!(Some("unlock"), reasoner.current_plan());
+
+        assert_eq!(1, run(&mut reasoner, 1).len());
+        assert_eq!(Some("open then walk in"), reasoner.current_plan());
+
+        run(&mut reasoner, 1);
+        assert!(reasoner.beliefs().believes(&Fact::InRoom));
+        assert_eq!(vec![Goal::EnterRoom], reasoner.take_achieved());
+    }
+
+    #[test]
+    fn it_should_fail_a_goal_without_applicable_plan() {
+        let mut reasoner = reasoner();
+        reasoner.adopt(Goal::EnterRoom);
+        reasoner.adopt(Goal::OpenDoor);
+
+        assert!(run(&mut reasoner, 3).is_empty());
+
+        assert_eq!(vec![Goal::EnterRoom, Goal::OpenDoor], reasoner.take_failed());
+        assert!(reasoner.take_achieved().is_empty());
+        assert!(reasoner.is_idle());
+    }
+
+    #[test]
+    fn it_should_try_another_plan_when_one_fails() {
+        let mut reasoner = reasoner();
+        reasoner.add_plan(Plan::new("force", Goal::OpenDoor)
+            .step(|_| Outcome::Next)
+            .step(|_| Outcome::Fail));
+        reasoner.add_plan(Plan::new("knock", Goal::OpenDoor)
+            .step(|deliberation| {
+                deliberation.adopt(Goal::EnterRoom);
+                deliberation.believe(Fact::DoorOpen);
+                Outcome::Next
+            }));
+        reasoner.adopt(Goal::OpenDoor);
+
+        reasoner.step();
+        assert_eq!(Some("force"), reasoner.current_plan());
+        assert_eq!(1, reasoner.intentions()[0].step);
+
+        reasoner.step();
+        assert_eq!(Some("knock"), reasoner.current_plan());
+        reasoner.step();
+        assert_eq!(vec![Goal::OpenDoor], reasoner.take_achieved());
+
+        reasoner.step();
+        assert_eq!(vec![Goal::EnterRoom], reasoner.take_achieved());
+        assert!(reasoner.beliefs().believes(&Fact::InRoom));
+        assert!(reasoner.take_failed().is_empty());
+    }
+}
@@ -0,0 +1,699 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::mem;
+use std::time::Duration;
+
+use mailbox::{Mailbox, MessageTemplate};
+use message::{Content, Message, Timestamp};
+
+pub type BehaviourId = usize;
+
+/// What a behaviour sees of its agent during one of its steps.
+pub struct Context<C> {
+    now: Timestamp,
+    tick: u64,
+    inbox: Mailbox<C>,
+    outbox: Vec<Message<C>>,
+    added: Vec<(BehaviourId, Box<dyn Behaviour<C>>)>,
+    removed: Vec<BehaviourId>,
+    next_id: BehaviourId,
+    finished: bool,
+    exit_code: i32,
+}
+
+impl<C> Context<C> {
+    pub fn now(&self) -> Timestamp {
+        self.now
+    }
+
+    /// Number of scheduling rounds of the agent, this one included.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Take the next message received by the agent and not consumed yet by its behaviours, by priority.
+    pub fn receive(&mut self) -> Option<Message<C>> {
+        self.inbox.receive(&MessageTemplate::any())
+    }
+
+    /// Take the next message matching the template, the others stay for the next behaviours.
+    pub fn receive_matching(&mut self, template: &MessageTemplate) -> Option<Message<C>> {
+        self.inbox.receive(template)
+    }
+
+    pub fn send(&mut self, message: Message<C>) {
+        self.outbox.push(message);
+    }
+
+    /// Add a behaviour to the agent, it takes its first step in the next round.
+    pub fn add(&mut self, behaviour: Box<dyn Behaviour<C>>) -> BehaviourId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.added.push((id, behaviour));
+        id
+    }
+
+    /// Remove a behaviour of the agent at the end of the round.
+    pub fn remove(&mut self, id: BehaviourId) {
+        self.removed.push(id);
+    }
+
+    /// End the running behaviour after this step.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// End the running behaviour after this step with the exit code, read by a `Fsm` to choose its next state.
+    pub fn exit(&mut self, code: i32) {
+        self.exit_code = code;
+        self.finished = true;
+    }
+}
+
+/// Task run by the agent step by step: the system calls `action` once in each of its rounds
+/// until `done` returns true.
+pub trait Behaviour<C>: Send {
+    fn action(&mut self, context: &mut Context<C>);
+
+    fn done(&self) -> bool;
+
+    /// Exit code of the behaviour once done.
+    fn exit_code(&self) -> i32 { 0 }
+
+    /// Bring the behaviour back to its initial state, before a `Fsm` enters its state again.
+    fn reset(&mut self) {}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Schedule {
+    Once,
+    Cyclic,
+    Ticks(u64),
+    Every(Duration),
+    After(Duration),
+}
+
+/// Behaviour running a closure on a schedule, built by `one_shot`, `cyclic`, `ticker`, `ticker_every` and `waker`.
+pub struct Simple<C, F> {
+    schedule: Schedule,
+    action: F,
+    /// Steps since the last run of a ticker.
+    steps: u64,
+    next_run: Option<Timestamp>,
+    done: bool,
+    exit_code: i32,
+    content: PhantomData<fn(&mut Context<C>)>,
+}
+
+impl<C, F> Simple<C, F> {
+    fn new(schedule: Schedule, action: F) -> Self {
+        Simple { schedule, action, steps: 0, next_run: None, done: false, exit_code: 0, content: PhantomData }
+    }
+}
+
+impl<C, F> Behaviour<C> for Simple<C, F> where F: FnMut(&mut Context<C>) + Send {
+    fn action(&mut self, context: &mut Context<C>) {
+        self.steps += 1;
+        let now = context.now;
+
+        let run = match self.schedule {
+            Schedule::Once | Schedule::Cyclic => true,
+            Schedule::Ticks(n) => {
+                if self.steps < n.max(1) {
+                    false
+                } else {
+                    self.steps = 0;
+                    true
+                }
+            },
+            Schedule::Every(period) | Schedule::After(period) => {
+                let next_run = *self.next_run.get_or_insert(now + period);
+                if now < next_run {
+                    false
+                } else {
+                    self.next_run = Some(now + period);
+                    true
+                }
+            },
+        };
+        if !run {
+            return;
+        }
+
+        (self.action)(context);
+
+        if context.finished || matches!(self.schedule, Schedule::Once | Schedule::After(_)) {
+            self.done = true;
+            self.exit_code = context.exit_code;
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.done
+    }
+
+    fn exit_code(&self) -> i32 {
+        self.exit_code
+    }
+
+    fn reset(&mut self) {
+        self.steps = 0;
+        self.next_run = None;
+        self.done = false;
+        self.exit_code = 0;
+    }
+}
+
+/// Run the closure once.
+pub fn one_shot<C, F>(action: F) -> Simple<C, F> where F: FnMut(&mut Context<C>) + Send {
+    Simple::new(Schedule::Once, action)
+}
+
+/// Run the closure in every round until it calls `finish`.
+pub fn cyclic<C, F>(action: F) -> Simple<C, F> where F: FnMut(&mut Context<C>) + Send {
+    Simple::new(Schedule::Cyclic, action)
+}
+
+/// Run the closure every `ticks` rounds until it calls `finish`.
+pub fn ticker<C, F>(ticks: u64, action: F) -> Simple<C, F> where F: FnMut(&mut Context<C>) + Send {
+    Simple::new(Schedule::Ticks(ticks), action)
+}
+
+/// Run the closure each time the period elapses, counted from the first round of the behaviour.
+pub fn ticker_every<C, F>(period: Duration, action: F) -> Simple<C, F> where F: FnMut(&mut Context<C>) + Send {
+    Simple::new(Schedule::Every(period), action)
+}
+
+/// Run the closure once, the delay after the first round of the behaviour.
+pub fn waker<C, F>(delay: Duration, action: F) -> Simple<C, F> where F: FnMut(&mut Context<C>) + Send {
+    Simple::new(Schedule::After(delay), action)
+}
+
+/// Run the children one after the other, each one until it's done.
+pub struct Sequential<C> {
+    children: Vec<Box<dyn Behaviour<C>>>,
+    current: usize,
+}
+
+impl<C> Sequential<C> {
+    pub fn new() -> Self {
+        Sequential { children: Vec::new(), current: 0 }
+    }
+
+    pub fn then<B: Behaviour<C> + 'static>(mut self, child: B) -> Self {
+        self.children.push(Box::new(child));
+        self
+    }
+}
+
+impl<C> Default for Sequential<C> {
+    fn default() -> Self {
+        Sequential::new()
+    }
+}
+
+impl<C> Behaviour<C> for Sequential<C> {
+    fn action(&mut self, context: &mut Context<C>) {
+        if let Some(child) = self.children.get_mut(self.current) {
+            run_child(child.as_mut(), context);
+            if child.done() {
+                self.current += 1;
+            }
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.current >= self.children.len()
+    }
+
+    fn exit_code(&self) -> i32 {
+        self.children.last().map_or(0, |child| child.exit_code())
+    }
+
+    fn reset(&mut self) {
+        self.current = 0;
+        for child in self.children.iter_mut() {
+            child.reset();
+        }
+    }
+}
+
+/// When a `Parallel` behaviour ends.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParallelEnd {
+    /// Once all its children are done.
+    All,
+    /// As soon as one of its children is done.
+    Any,
+}
+
+/// Run one step of each of its children in every round.
+pub struct Parallel<C> {
+    end: ParallelEnd,
+    children: Vec<Box<dyn Behaviour<C>>>,
+}
+
+impl<C> Parallel<C> {
+    pub fn new(end: ParallelEnd) -> Self {
+        Parallel { end, children: Vec::new() }
+    }
+
+    pub fn with<B: Behaviour<C> + 'static>(mut self, child: B) -> Self {
+        self.children.push(Box::new(child));
+        self
+    }
+}
+
+impl<C> Behaviour<C> for Parallel<C> {
+    fn action(&mut self, context: &mut Context<C>) {
+        for child in self.children.iter_mut().filter(|child| !child.done()) {
+            run_child(child.as_mut(), context);
+        }
+    }
+
+    fn done(&self) -> bool {
+        match self.end {
+            ParallelEnd::All => self.children.iter().all(|child| child.done()),
+            ParallelEnd::Any => self.children.is_empty() || self.children.iter().any(|child| child.done()),
+        }
+    }
+
+    fn reset(&mut self) {
+        for child in self.children.iter_mut() {
+            child.reset();
+        }
+    }
+}
+
+/// Finite state machine whose states are behaviours. Once the behaviour of a state is done,
+/// its exit code chooses the transition to the next state, the machine ends in a last state.
+pub struct Fsm<C> {
+    states: HashMap<String, Box<dyn Behaviour<C>>>,
+    first: Option<String>,
+    last: Vec<String>,
+    transitions: HashMap<(String, i32), String>,
+    default_transitions: HashMap<String, String>,
+    current: Option<String>,
+    done: bool,
+    exit_code: i32,
+}
+
+impl<C> Fsm<C> {
+    pub fn new() -> Self {
+        Fsm {
+            states: HashMap::new(),
+            first: None,
+            last: Vec::new(),
+            transitions: HashMap::new(),
+            default_transitions: HashMap::new(),
+            current: None,
+            done: false,
+            exit_code: 0,
+        }
+    }
+
+    pub fn first_state<B: Behaviour<C> + 'static>(mut self, name: &str, behaviour: B) -> Self {
+        self.first = Some(name.to_string());
+        self.state(name, behaviour)
+    }
+
+    pub fn state<B: Behaviour<C> + 'static>(mut self, name: &str, behaviour: B) -> Self {
+        self.states.insert(name.to_string(), Box::new(behaviour));
+        self
+    }
+
+    pub fn last_state<B: Behaviour<C> + 'static>(mut self, name: &str, behaviour: B) -> Self {
+        self.last.push(name.to_string());
+        self.state(name, behaviour)
+    }
+
+    /// Go from the state `from` to the state `to` when `from` ends with the exit code.
+    pub fn transition(mut self, from: &str, to: &str, exit_code: i32) -> Self {
+        self.transitions.insert((from.to_string(), exit_code), to.to_string());
+        self
+    }
+
+    /// Go from the state `from` to the state `to` when no transition matches its exit code.
+    pub fn default_transition(mut self, from: &str, to: &str) -> Self {
+        self.default_transitions.insert(from.to_string(), to.to_string());
+        self
+    }
+
+    /// Name of the current state.
+    pub fn current_state(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+}
+
+impl<C> Default for Fsm<C> {
+    fn default() -> Self {
+        Fsm::new()
+    }
+}
+
+impl<C> Behaviour<C> for Fsm<C> {
+    fn action(&mut self, context: &mut Context<C>) {
+        if self.current.is_none() {
+            self.current = self.first.clone();
+        }
+        let current = match self.current.clone() {
+            Some(current) => current,
+            None => {
+                warn!("The state machine has no first state");
+                self.done = true;
+                return;
+            },
+        };
+
+        let exit_code = match self.states.get_mut(&current) {
+            Some(state) => {
+                run_child(state.as_mut(), context);
+                if !state.done() {
+                    return;
+                }
+                state.exit_code()
+            },
+            None => {
+                warn!("The state machine has no state {}", current);
+                self.done = true;
+                return;
+            },
+        };
+
+        if self.last.contains(&current) {
+            self.done = true;
+            self.exit_code = exit_code;
+            return;
+        }
+
+        let next = self.transitions.get(&(current.clone(), exit_code))
+            .or_else(|| self.default_transitions.get(&current))
+            .cloned();
+
+        match next.and_then(|next| self.states.get_mut(&next).map(|state| (next, state))) {
+            Some((next, state)) => {
+                state.reset();
+                self.current = Some(next);
+            },
+            None => {
+                warn!("The state machine has no transition from {} with the exit code {}", current, exit_code);
+                self.done = true;
+                self.exit_code = exit_code;
+            },
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.done
+    }
+
+    fn exit_code(&self) -> i32 {
+        self.exit_code
+    }
+
+    fn reset(&mut self) {
+        self.current = None;
+        self.done = false;
+        self.exit_code = 0;
+        for state in self.states.values_mut() {
+            state.reset();
+        }
+    }
+}
+
+/// Run one step of a child behaviour, the exit request of the child doesn't end its parent.
+fn run_child<C>(child: &mut dyn Behaviour<C>, context: &mut Context<C>) {
+    let finished = mem::replace(&mut context.finished, false);
+    let exit_code = mem::replace(&mut context.exit_code, 0);
+
+    child.action(context);
+
+    context.finished = finished;
+    context.exit_code = exit_code;
+}
+
+/// Behaviours of an agent, the system runs them in place of `act`.
+pub struct Behaviours<C> {
+    behaviours: Vec<(BehaviourId, Box<dyn Behaviour<C>>)>,
+    inbox: Mailbox<C>,
+    next_id: BehaviourId,
+    tick: u64,
+}
+
+impl<C: Content> Behaviours<C> {
+    pub fn new() -> Self {
+        Behaviours {
+            behaviours: Vec::new(),
+            inbox: Mailbox::new(),
+            next_id: 0,
+            tick: 0,
+        }
+    }
+
+    /// Behaviours whose inbox protects low priority messages from starvation, see `Mailbox::with_aging`.
+    pub fn with_aging(every: u64) -> Self {
+        Behaviours { inbox: Mailbox::with_aging(every), ..Behaviours::new() }
+    }
+
+    pub fn add<B: Behaviour<C> + 'static>(&mut self, behaviour: B) -> BehaviourId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.behaviours.push((id, Box::new(behaviour)));
+        id
+    }
+
+    /// Remove the behaviour, returns false if it's already done or removed.
+    pub fn remove(&mut self, id: BehaviourId) -> bool {
+        let len = self.behaviours.len();
+        self.behaviours.retain(|&(behaviour_id, _)| behaviour_id != id);
+        self.behaviours.len() != len
+    }
+
+    pub fn contains(&self, id: BehaviourId) -> bool {
+        self.behaviours.iter().any(|&(behaviour_id, _)| behaviour_id == id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.behaviours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.behaviours.is_empty()
+    }
+
+    /// Keep a message received by the agent until one of its behaviours consumes it.
+    pub fn post(&mut self, message: Message<C>) {
+        self.inbox.push(message);
+    }
+
+    /// Messages received by the agent and not consumed yet by its behaviours.
+    pub fn mailbox(&mut self) -> &mut Mailbox<C> {
+        &mut self.inbox
+    }
+
+    /// Run one step of each behaviour, in the order they were added, and remove the ones done.
+    /// Returns the messages they sent.
+    pub fn run(&mut self, now: Timestamp) -> Vec<Message<C>> {
+        self.tick += 1;
+
+        let mut context = Context {
+            now,
+            tick: self.tick,
+            inbox: mem::take(&mut self.inbox),
+            outbox: Vec::new(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            next_id: self.next_id,
+            finished: false,
+            exit_code: 0,
+        };
+
+        for &mut (_, ref mut behaviour) in self.behaviours.iter_mut() {
+            context.finished = false;
+            context.exit_code = 0;
+            behaviour.action(&mut context);
+        }
+
+        // A behaviour added in this round can also be removed in it.
+        let removed = context.removed;
+        self.behaviours.extend(context.added);
+        self.behaviours.retain(|&(id, ref behaviour)| !behaviour.done() && !removed.contains(&id));
+        self.next_id = context.next_id;
+        self.inbox = context.inbox;
+
+        context.outbox
+    }
+}
+
+impl<C: Content> Default for Behaviours<C> {
+    fn default() -> Self {
+        Behaviours::new()
+    }
+}
+
+#[cfg(test)]
+mod test_behaviour {
+
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use agent::AgentId;
+    use message::Performative;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Step(String);
+
+    impl Content for Step {}
+
+    fn step(name: &str) -> Message<Step> {
+        Message::inform((0, AgentId::new(0, 0))).content(Step(name.to_string())).build()
+    }
+
+    fn names(messages: Vec<Message<Step>>) -> Vec<String> {
+        messages.into_iter().map(|m| m.content.0).collect()
+    }
+
+    fn run(behaviours: &mut Behaviours<Step>, rounds: u64) -> Vec<String> {
+        (0..rounds).flat_map(|round| names(behaviours.run(Timestamp(round)))).collect()
+    }
+
+    #[test]
+    fn it_should_run_a_one_shot_behaviour_once() {
+        let mut behaviours = Behaviours::new();
+        behaviours.add(one_shot(|context| context.send(step("once"))));
+
+        assert_eq!(vec!["once"], run(&mut behaviours, 3));
+        assert!(behaviours.is_empty());
+    }
+
+    #[test]
+    fn it_should_run_a_cyclic_behaviour_until_it_finishes() {
+        let mut behaviours = Behaviours::new();
+        behaviours.add(cyclic(|context: &mut Context<Step>| {
+            context.send(step("cycle"));
+            if context.tick() == 3 {
+                context.finish();
+            }
+        }));
+
+        assert_eq!(vec!["cycle"; 3], run(&mut behaviours, 5));
+        assert!(behaviours.is_empty());
+    }
+
+    #[test]
+    fn it_should_run_tickers_and_wakers_on_their_schedule() {
+        let mut behaviours = Behaviours::new();
+        behaviours.add(ticker(2, |context| context.send(step("tick"))));
+        behaviours.add(ticker_every(Duration::from_millis(3), |context| context.send(step("every"))));
+        behaviours.add(waker(Duration::from_millis(4), |context| context.send(step("wake"))));
+
+        let sent: Vec<Vec<String>> = (0..7).map(|round| names(behaviours.run(Timestamp(round)))).collect();
+
+        assert_eq!(vec![vec![], vec!["tick"], vec![], vec!["tick", "every"], vec!["wake"], vec!["tick"], vec!["every"]], sent);
+        assert_eq!(2, behaviours.len());
+    }
+
+    #[test]
+    fn it_should_add_and_remove_behaviours_at_runtime() {
+        let mut behaviours = Behaviours::new();
+        let cycle = behaviours.add(cyclic(|context| context.send(step("cycle"))));
+        behaviours.add(one_shot(move |context: &mut Context<Step>| {
+            context.add(Box::new(one_shot(|context| context.send(step("added")))));
+            context.remove(cycle);
+        }));
+
+        assert_eq!(vec!["cycle", "added"], run(&mut behaviours, 3));
+        assert!(!behaviours.contains(cycle));
+
+        let cycle = behaviours.add(cyclic(|context| context.send(step("cycle"))));
+        assert!(behaviours.remove(cycle));
+        assert!(!behaviours.remove(cycle));
+    }
+
+    #[test]
+    fn it_should_remove_a_behaviour_added_in_the_same_round() {
+        let mut behaviours = Behaviours::new();
+        behaviours.add(one_shot(|context: &mut Context<Step>| {
+            let added = context.add(Box::new(cyclic(|context| context.send(step("added")))));
+            context.remove(added);
+        }));
+
+        assert!(run(&mut behaviours, 3).is_empty());
+        assert!(behaviours.is_empty());
+    }
+
+    #[test]
+    fn it_should_run_sequential_and_parallel_children() {
+        let mut behaviours = Behaviours::new();
+        behaviours.add(Sequential::new()
+            .then(one_shot(|context| context.send(step("first"))))
+            .then(Parallel::new(ParallelEnd::Any)
+                .with(cyclic(|context| context.send(step("forever"))))
+                .with(ticker(2, |context: &mut Context<Step>| {
+                    context.send(step("once"));
+                    context.finish();
+                }))));
+
+        assert_eq!(vec!["first", "forever", "forever", "once"], run(&mut behaviours, 5));
+        assert!(behaviours.is_empty());
+    }
+
+    #[test]
+    fn it_should_follow_the_transitions_of_a_state_machine() {
+        let attempts = Arc::new(Mutex::new(0));
+        let counter = attempts.clone();
+
+        let mut behaviours = Behaviours::new();
+        behaviours.add(Fsm::new()
+            .first_state("try", one_shot(move |context: &mut Context<Step>| {
+                let mut attempts = counter.lock().unwrap();
+                *attempts += 1;
+                context.send(step("try"));
+                context.exit(if *attempts < 3 { 1 } else { 0 });
+            }))
+            .state("wait", one_shot(|context| context.send(step("wait"))))
+            .last_state("done", one_shot(|context| context.send(step("done"))))
+            .transition("try", "wait", 1)
+            .default_transition("try", "done")
+            .default_transition("wait", "try"));
+
+        assert_eq!(vec!["try", "wait", "try", "wait", "try", "done"], run(&mut behaviours, 10));
+        assert_eq!(3, *attempts.lock().unwrap());
+        assert!(behaviours.is_empty());
+    }
+
+    #[test]
+    fn it_should_let_behaviours_receive_the_messages_posted() {
+        let mut behaviours = Behaviours::new();
+        behaviours.add(cyclic(|context: &mut Context<Step>| {
+            while let Some(message) = context.receive() {
+                context.send(message.create_reply(Performative::Agree, Step("ack".to_string())));
+            }
+        }));
+        behaviours.post(step("hello"));
+
+        assert_eq!(vec!["ack"], run(&mut behaviours, 2));
+    }
+
+    #[test]
+    fn it_should_age_the_messages_waiting_in_the_inbox() {
+        let prioritized = |name: &str, priority: u8| {
+            let mut message = step(name);
+            message.priority = priority;
+            message
+        };
+
+        let mut behaviours = Behaviours::with_aging(1);
+        behaviours.add(cyclic(|context: &mut Context<Step>| {
+            if let Some(message) = context.receive() {
+                context.send(message);
+            }
+        }));
+        behaviours.post(prioritized("low", 0));
+        behaviours.post(prioritized("high", 1));
+        assert_eq!(vec!["high"], names(behaviours.run(Timestamp(0))));
+
+        behaviours.post(prioritized("higher", 1));
+        assert_eq!(vec!["low"], names(behaviours.run(Timestamp(1))));
+    }
+}
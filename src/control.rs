@@ -0,0 +1,57 @@
+use bincode;
+use uuid::Uuid;
+
+use agent::AgentId;
+use agent_system::{ReplyWindow, SystemId};
+use message::*;
+
+/// Messages exchanged between the systems themselves, distinct from the agents `Content`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum ControlMessage {
+    /// Ask the recipient system to spawn `count` agents with its factory.
+    SpawnAgents { request_id: Uuid, count: usize },
+    /// Reply to `SpawnAgents` with the ids of the agents spawned.
+    AgentsSpawned { request_id: Uuid, agents: Vec<AgentId> },
+    /// Carry a serialized agent moving from the sender system to the recipient one, with the
+    /// messages it can still reply to.
+    MigrateAgent { origin: AgentId, agent: Vec<u8>, replies: Vec<(Id, ReplyWindow)> },
+    /// Reply to `MigrateAgent` with the id of the agent in the recipient system.
+    AgentMigrated { origin: AgentId, agent_id: AgentId },
+    /// Reply to `MigrateAgent` when the recipient system can't reinstantiate the agent.
+    MigrationFailed { origin: AgentId },
+    /// Reply to a late `AgentMigrated`, once the agent came back in the sender system: the
+    /// recipient system removes its copy of the agent.
+    MigrationCancelled { agent_id: AgentId },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Control {
+    pub sender: SystemId,
+    pub recipient: SystemId,
+    pub message: ControlMessage,
+}
+
+impl Control {
+    pub fn new(sender: SystemId, recipient: SystemId, message: ControlMessage) -> Self {
+        Control {
+            sender,
+            recipient,
+            message,
+        }
+    }
+
+    pub fn serialize(&self) -> Result<Vec<u8>, Box<bincode::ErrorKind>> {
+        bincode::serialize(self)
+    }
+
+    pub fn deserialize(msg: &[u8]) -> Result<Self, Box<bincode::ErrorKind>> {
+        bincode::deserialize(msg)
+    }
+}
+
+/// Everything a system can receive from the channel of a local system.
+#[derive(Clone, Debug)]
+pub enum Packet<C> {
+    Agent(Message<C>),
+    Control(Control),
+}
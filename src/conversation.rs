@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    time::Duration,
+};
+
+use agent::AgentId;
+use agent_system::SystemId;
+use message::{Id, Message, Performative, Recipient, Timestamp};
+
+/// FIPA interaction protocols an agent can follow in a conversation.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum InteractionProtocol {
+    Request,
+    Query,
+    ContractNet,
+    Subscribe,
+    EnglishAuction,
+    DutchAuction,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConversationState {
+    Open,
+    Closed,
+}
+
+/// A conversation an agent takes part in.
+#[derive(Clone, Debug)]
+pub struct Conversation {
+    pub id: Id,
+    pub protocol: Option<InteractionProtocol>,
+    /// Other agents having sent or received a message of the conversation.
+    pub participants: Vec<(SystemId, AgentId)>,
+    pub last_performative: Performative,
+    pub state: ConversationState,
+    pub started: Timestamp,
+    pub updated: Timestamp,
+}
+
+/// Conversations of an agent by their `conversation_id`.
+/// The system records the messages of an agent exposing its table through `Agent::conversations`.
+#[derive(Clone, Debug, Default)]
+pub struct ConversationTable {
+    conversations: HashMap<Id, Conversation>,
+}
+
+impl ConversationTable {
+    pub fn new() -> Self {
+        ConversationTable {
+            conversations: HashMap::new(),
+        }
+    }
+
+    /// Record a message sent by the agent. Messages without `conversation_id` are ignored.
+    pub fn record_sent<C>(&mut self, message: &Message<C>, now: Timestamp) {
+        let participant = match message.recipient {
+            Recipient::Agent { system_id, agent_id } => Some((system_id, agent_id)),
+            Recipient::Broadcast { .. } => None,
+        };
+
+        self.record(message, participant, now);
+    }
+
+    /// Record a message received by the agent. Messages without `conversation_id` are ignored.
+    pub fn record_received<C>(&mut self, message: &Message<C>, now: Timestamp) {
+        self.record(message, Some(message.sender), now);
+    }
+
+    fn record<C>(&mut self, message: &Message<C>, participant: Option<(SystemId, AgentId)>, now: Timestamp) {
+        let id = match message.conversation_id {
+            Some(id) => id,
+            None => return,
+        };
+
+        let conversation = self.conversations.entry(id).or_insert_with(|| Conversation {
+            id,
+            protocol: message.protocol,
+            participants: Vec::new(),
+            last_performative: message.performative.clone(),
+            state: ConversationState::Open,
+            started: now,
+            updated: now,
+        });
+
+        if let Some(participant) = participant {
+            if !conversation.participants.contains(&participant) {
+                conversation.participants.push(participant);
+            }
+        }
+
+        if conversation.protocol.is_none() {
+            conversation.protocol = message.protocol;
+        }
+
+        conversation.last_performative = message.performative.clone();
+        conversation.updated = now;
+    }
+
+    pub fn get(&self, id: Id) -> Option<&Conversation> {
+        self.conversations.get(&id)
+    }
+
+    pub fn open_conversations(&self) -> impl Iterator<Item=&Conversation> {
+        self.conversations.values().filter(|c| c.state == ConversationState::Open)
+    }
+
+    /// Mark the conversation as closed, it stays in the table until it expires or is removed.
+    pub fn close(&mut self, id: Id) -> bool {
+        match self.conversations.get_mut(&id) {
+            Some(conversation) => {
+                conversation.state = ConversationState::Closed;
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn remove(&mut self, id: Id) -> Option<Conversation> {
+        self.conversations.remove(&id)
+    }
+
+    /// Remove and return the conversations without any message since `max_idle`.
+    pub fn expire(&mut self, now: Timestamp, max_idle: Duration) -> Vec<Conversation> {
+        let stale: Vec<Id> = self.conversations
+            .values()
+            .filter(|c| c.updated + max_idle <= now)
+            .map(|c| c.id)
+            .collect();
+
+        stale.into_iter()
+            .filter_map(|id| self.conversations.remove(&id))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.conversations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conversations.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test_conversation {
+
+    use super::*;
+    use message::{Content, new_id};
+
+    #[derive(Serialize, Deserialize, Clone)]
+    struct Foo;
+
+    impl Content for Foo {}
+
+    fn request(conversation_id: Id) -> Message<Foo> {
+        Message::request((1, AgentId::new(3, 0)))
+            .protocol(InteractionProtocol::Request)
+            .conversation(conversation_id)
+            .content(Foo)
+            .build()
+    }
+
+    #[test]
+    fn it_should_record_the_participants_and_the_last_performative() {
+        let mut table = ConversationTable::new();
+        let id = new_id();
+        let now = Timestamp(1000);
+
+        let request = request(id);
+        table.record_sent(&request, now);
+
+        let mut agree = Message::reply_to(&request).performative(Performative::Agree).content(Foo).build();
+        agree.set_sender((1, AgentId::new(3, 0)));
+        table.record_received(&agree, now + Duration::from_secs(1));
+
+        let conversation = table.get(id).expect("Should record the conversation");
+
+        assert_eq!(Some(InteractionProtocol::Request), conversation.protocol);
+        assert_eq!(vec![(1, AgentId::new(3, 0))], conversation.participants);
+        assert_eq!(Performative::Agree, conversation.last_performative);
+        assert_eq!(now, conversation.started);
+        assert_eq!(Timestamp(2000), conversation.updated);
+    }
+
+    #[test]
+    fn it_should_ignore_the_messages_out_of_a_conversation() {
+        let mut table = ConversationTable::new();
+        let message = Message::inform(Recipient::Broadcast { system_id: None }).content(Foo).build();
+
+        table.record_sent(&message, Timestamp(0));
+
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn it_should_list_only_the_open_conversations() {
+        let mut table = ConversationTable::new();
+        let open = new_id();
+        let closed = new_id();
+
+        table.record_sent(&request(open), Timestamp(0));
+        table.record_sent(&request(closed), Timestamp(0));
+        assert!(table.close(closed));
+
+        let ids: Vec<Id> = table.open_conversations().map(|c| c.id).collect();
+
+        assert_eq!(vec![open], ids);
+        assert_eq!(2, table.len());
+    }
+
+    #[test]
+    fn it_should_expire_the_idle_conversations() {
+        let mut table = ConversationTable::new();
+        let stale = new_id();
+        let active = new_id();
+
+        table.record_sent(&request(stale), Timestamp(0));
+        table.record_sent(&request(active), Timestamp(50_000));
+
+        let expired = table.expire(Timestamp(60_000), Duration::from_secs(30));
+
+        assert_eq!(1, expired.len());
+        assert_eq!(stale, expired[0].id);
+        assert!(table.get(active).is_some());
+    }
+}
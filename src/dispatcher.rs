@@ -2,6 +2,7 @@ use zmq::{Socket, Context as ZmqContext, PUB};
 
 use message::*;
 use agent_system::SystemId;
+use control::{Control, Packet};
 
 use std::{
     collections::HashMap,
@@ -15,9 +16,10 @@ const NO_FLAGS: i32 = 0;
 const SEND_TO_AGENT: u8 = 0;
 const BROADCAST_TO_SYSTEM: u8 = 1;
 const BROADCAST_TO_ALL: u8 = 2;
+const SYSTEM_CONTROL: u8 = 3;
 
 pub struct Dispatcher<C: Content> {
-    local_observers: HashMap<u8, Sender<Message<C>>>,
+    local_observers: HashMap<u8, Sender<Packet<C>>>,
     broadcast_publisher: Socket,
 }
 
@@ -40,7 +42,7 @@ impl <C: Content>Dispatcher<C> {
         }
     }
 
-    pub fn add_local_sender(&mut self, sys_id: u8, sender: Sender<Message<C>>) {
+    pub fn add_local_sender(&mut self, sys_id: u8, sender: Sender<Packet<C>>) {
         self.local_observers.insert(sys_id, sender);
     }
 
@@ -63,17 +65,37 @@ impl <C: Content>Dispatcher<C> {
         }
     }
 
+    pub fn dispatch_controls(&self, controls: Drain<Control>) {
+        for c in controls {
+            if let Some(observer) = self.local_observers.get(&c.recipient) {
+                debug!("send a control message to the local system {}", c.recipient);
+                log_if_error!(observer.send(Packet::Control(c)))
+            } else if let Ok(msg) = c.serialize() {
+                log_if_error!(self.broadcast_publisher
+                                .send_multipart(
+                                    &[
+                                        &[ SYSTEM_CONTROL, c.recipient ][..],
+                                        msg.as_slice(),
+                                    ]
+                                    , NO_FLAGS)
+                                )
+            } else {
+                error!("Error during serialize");
+            }
+        }
+    }
+
     fn forward_message_to_local_sytem(&self, message: Message<C>, system_id: SystemId) {
         if let Some(observer) = self.local_observers.get(&system_id) {
             debug!("send a message to a agent in the local system {}", system_id);
-            log_if_error!(observer.send(message))
+            log_if_error!(observer.send(Packet::Agent(message)))
         }
     }
 
     fn broadcast_message_to_local_systems(&self, message: &Message<C>) {
         for (_, observer) in self.local_observers.iter() {
             debug!("broadcast a message to all local observers systems");
-            log_if_error!(observer.send(message.clone()))
+            log_if_error!(observer.send(Packet::Agent(message.clone())))
         }
     }
 
@@ -131,6 +153,7 @@ fn create_publisher_chan_for_broadcast(zmq_ctx: &ZmqContext, addr: SocketAddr) -
 #[cfg(test)]
 mod test {
     use super::*;
+    use agent::AgentId;
     use std::sync::mpsc;
 
     #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -140,17 +163,10 @@ mod test {
 
     #[test]
     fn it_should_detect_that_is_a_message_for_a_remote_system() {
-        let message = Message::new(
-            Performative::Confirm,
-            Recipient::Broadcast{ system_id: None },
-            3,
-            0,
-            None,
-            None,
-            None,
-            None,
-            EmptyPayload{},
-        );
+        let message = Message::builder(Performative::Confirm, Recipient::Broadcast{ system_id: None })
+            .ontology(3)
+            .content(EmptyPayload{})
+            .build();
 
         let zmq_ctx = ZmqContext::new();
         let addr = "127.0.0.1:8080".parse().expect("Addr error");
@@ -163,17 +179,10 @@ mod test {
     fn it_should_detect_that_is_a_message_for_a_local_known_system() {
         let local_system_id = 1;
 
-        let message = Message::new(
-            Performative::Confirm,
-            Recipient::Broadcast{ system_id: Some(local_system_id) },
-            3,
-            0,
-            None,
-            None,
-            None,
-            None,
-            EmptyPayload{},
-        );
+        let message = Message::builder(Performative::Confirm, Recipient::Broadcast{ system_id: Some(local_system_id) })
+            .ontology(3)
+            .content(EmptyPayload{})
+            .build();
 
         let zmq_ctx = ZmqContext::new();
         let addr = "127.0.0.1:8081".parse().expect("Addr error");
@@ -186,17 +195,10 @@ mod test {
 
     #[test]
     fn it_should_detect_that_his_a_message_for_a_remote_system() {
-        let message = Message::new(
-            Performative::Confirm,
-            Recipient::Broadcast{ system_id: Some(255) },
-            3,
-            0,
-            None,
-            None,
-            None,
-            None,
-            EmptyPayload{},
-        );
+        let message = Message::builder(Performative::Confirm, Recipient::Broadcast{ system_id: Some(255) })
+            .ontology(3)
+            .content(EmptyPayload{})
+            .build();
 
         let zmq_ctx = ZmqContext::new();
         let addr = "127.0.0.1:8082".parse().expect("Addr error");
@@ -207,17 +209,10 @@ mod test {
 
     #[test]
     fn it_should_detect_that_his_a_message_for_an_agent_in_a_remote_system() {
-        let message = Message::new(
-            Performative::Confirm,
-            Recipient::Agent{ system_id: 42, agent_id: 42 },
-            3,
-            0,
-            None,
-            None,
-            None,
-            None,
-            EmptyPayload{},
-        );
+        let message = Message::builder(Performative::Confirm, Recipient::Agent{ system_id: 42, agent_id: AgentId::new(42, 0) })
+            .ontology(3)
+            .content(EmptyPayload{})
+            .build();
 
         let zmq_ctx = ZmqContext::new();
         let addr = "127.0.0.1:8083".parse().expect("Addr error");
@@ -230,17 +225,10 @@ mod test {
     fn it_should_detect_that_his_a_message_for_an_agent_in_a_local_system() {
         let local_system_id = 1;
 
-        let message = Message::new(
-            Performative::Confirm,
-            Recipient::Agent{ system_id: local_system_id, agent_id: 0 },
-            3,
-            0,
-            None,
-            None,
-            None,
-            None,
-            EmptyPayload{},
-        );
+        let message = Message::builder(Performative::Confirm, Recipient::Agent{ system_id: local_system_id, agent_id: AgentId::new(0, 0) })
+            .ontology(3)
+            .content(EmptyPayload{})
+            .build();
 
         let zmq_ctx = ZmqContext::new();
         let addr = "127.0.0.1:8084".parse().expect("Addr error");
@@ -12,9 +12,19 @@ extern crate bincode;
 pub mod agent;
 pub mod agent_system;
 pub mod agent_factory;
+pub mod bdi;
+pub mod behaviour;
+pub mod control;
+pub mod conversation;
+pub mod mailbox;
 pub mod message;
+pub mod protocol;
+pub mod supervision;
+pub mod timer;
 
 mod monitoring;
 mod message_collector;
 mod dispatcher;
+mod supervisor;
+mod timer_wheel;
 mod utils;
\ No newline at end of file
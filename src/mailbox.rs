@@ -0,0 +1,250 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use agent::AgentId;
+use agent_system::SystemId;
+use message::{Id, Message, Performative};
+
+/// Pattern on the fields of a message, every field set must match.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct MessageTemplate {
+    performative: Option<Performative>,
+    sender: Option<(SystemId, AgentId)>,
+    conversation_id: Option<Id>,
+    ontology: Option<u8>,
+    in_reply_to: Option<Id>,
+}
+
+impl MessageTemplate {
+    /// Template matching any message.
+    pub fn any() -> Self {
+        MessageTemplate::default()
+    }
+
+    pub fn performative(mut self, performative: Performative) -> Self {
+        self.performative = Some(performative);
+        self
+    }
+
+    pub fn sender(mut self, sender: (SystemId, AgentId)) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    pub fn conversation(mut self, conversation_id: Id) -> Self {
+        self.conversation_id = Some(conversation_id);
+        self
+    }
+
+    pub fn ontology(mut self, ontology: u8) -> Self {
+        self.ontology = Some(ontology);
+        self
+    }
+
+    pub fn in_reply_to(mut self, reply_with: Id) -> Self {
+        self.in_reply_to = Some(reply_with);
+        self
+    }
+
+    pub fn matches<C>(&self, message: &Message<C>) -> bool {
+        matches_field(self.performative.as_ref(), &message.performative)
+            && matches_field(self.sender, message.sender)
+            && matches_field(self.conversation_id.map(Some), message.conversation_id)
+            && matches_field(self.ontology, message.ontology)
+            && matches_field(self.in_reply_to.map(Some), message.in_reply_to)
+    }
+}
+
+/// A field left unset in the template matches any value.
+fn matches_field<T: PartialEq>(field: Option<T>, value: T) -> bool {
+    match field {
+        Some(field) => field == value,
+        None => true,
+    }
+}
+
+/// Rank of the message then its rank of arrival in the mailbox, to keep FIFO order within a rank.
+type Key = (Reverse<i128>, u64);
+
+/// Messages received by an agent, kept until the agent takes them. Messages are taken by
+/// priority, the oldest first within a priority level.
+pub struct Mailbox<C> {
+    /// Messages in the order they are taken.
+    messages: BTreeMap<Key, Message<C>>,
+    /// Raise the priority of a waiting message by one level each time this number of messages is taken before it.
+    aging: Option<u64>,
+    next_seq: u64,
+    taken: u64,
+}
+
+impl<C> Mailbox<C> {
+    pub fn new() -> Self {
+        Mailbox {
+            messages: BTreeMap::new(),
+            aging: None,
+            next_seq: 0,
+            taken: 0,
+        }
+    }
+
+    /// Mailbox protecting low priority messages from starvation: a message waiting while `every` messages
+    /// are taken before it gains one priority level.
+    pub fn with_aging(every: u64) -> Self {
+        Mailbox { aging: Some(every.max(1)), ..Mailbox::new() }
+    }
+
+    pub fn push(&mut self, message: Message<C>) {
+        let key = (Reverse(self.rank(&message)), self.next_seq);
+        self.messages.insert(key, message);
+        self.next_seq += 1;
+    }
+
+    /// Rank of a message arriving now. With aging, the priority of a message counts as `every`
+    /// messages taken: the waiting messages gain a level at the same pace, so their order
+    /// doesn't change while they wait.
+    fn rank(&self, message: &Message<C>) -> i128 {
+        match self.aging {
+            None => i128::from(message.priority),
+            Some(every) => i128::from(message.priority) * i128::from(every) - i128::from(self.taken),
+        }
+    }
+
+    /// Key of the next message matching the template.
+    fn next(&self, template: &MessageTemplate) -> Option<Key> {
+        self.messages.iter()
+            .find(|&(_, message)| template.matches(message))
+            .map(|(&key, _)| key)
+    }
+
+    /// Take the next message matching the template, the others stay in the mailbox.
+    pub fn receive(&mut self, template: &MessageTemplate) -> Option<Message<C>> {
+        let key = self.next(template)?;
+        self.taken += 1;
+        self.messages.remove(&key)
+    }
+
+    /// Take every message matching the template, in the order `receive` would take them.
+    pub fn receive_all(&mut self, template: &MessageTemplate) -> Vec<Message<C>> {
+        let keys: Vec<Key> = self.messages.iter()
+            .filter(|&(_, message)| template.matches(message))
+            .map(|(&key, _)| key)
+            .collect();
+
+        self.taken += keys.len() as u64;
+        keys.into_iter()
+            .filter_map(|key| self.messages.remove(&key))
+            .collect()
+    }
+
+    /// Next message matching the template, left in the mailbox.
+    pub fn peek(&self, template: &MessageTemplate) -> Option<&Message<C>> {
+        self.next(template).map(|key| &self.messages[&key])
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+impl<C> Default for Mailbox<C> {
+    fn default() -> Self {
+        Mailbox::new()
+    }
+}
+
+#[cfg(test)]
+mod test_mailbox {
+
+    use super::*;
+    use message::{Content, new_id};
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Text(u8);
+
+    impl Content for Text {}
+
+    const ALICE: (SystemId, AgentId) = (0, AgentId { index: 0, generation: 0 });
+    const BOB: (SystemId, AgentId) = (1, AgentId { index: 0, generation: 0 });
+
+    fn message(performative: Performative, sender: (SystemId, AgentId), text: u8) -> Message<Text> {
+        let mut message = Message::builder(performative, (0, AgentId::new(1, 0))).content(Text(text)).build();
+        message.set_sender(sender);
+        message
+    }
+
+    #[test]
+    fn it_should_match_the_fields_of_the_template() {
+        let conversation_id = new_id();
+        let mut message = message(Performative::Inform, ALICE, 0);
+        message.conversation_id = Some(conversation_id);
+        message.ontology = 2;
+
+        assert!(MessageTemplate::any().matches(&message));
+        assert!(MessageTemplate::any().performative(Performative::Inform).sender(ALICE).matches(&message));
+        assert!(MessageTemplate::any().conversation(conversation_id).ontology(2).matches(&message));
+        assert!(!MessageTemplate::any().performative(Performative::Inform).sender(BOB).matches(&message));
+        assert!(!MessageTemplate::any().ontology(1).matches(&message));
+        assert!(!MessageTemplate::any().in_reply_to(new_id()).matches(&message));
+    }
+
+    #[test]
+    fn it_should_receive_selectively_and_keep_the_other_messages() {
+        let mut mailbox = Mailbox::new();
+        mailbox.push(message(Performative::Inform, ALICE, 0));
+        mailbox.push(message(Performative::Request, BOB, 1));
+        mailbox.push(message(Performative::Inform, BOB, 2));
+        mailbox.push(message(Performative::Request, ALICE, 3));
+
+        let from_bob = MessageTemplate::any().sender(BOB);
+        assert_eq!(Some(&Text(1)), mailbox.peek(&from_bob).map(|m| &m.content));
+        assert_eq!(Text(1), mailbox.receive(&from_bob).expect("Should receive the oldest message of bob").content);
+
+        let requests = mailbox.receive_all(&MessageTemplate::any().performative(Performative::Request));
+        assert_eq!(vec![Text(3)], requests.into_iter().map(|m| m.content).collect::<Vec<_>>());
+
+        assert_eq!(2, mailbox.len());
+        assert_eq!(Text(0), mailbox.receive(&MessageTemplate::any()).expect("Should keep the oldest message").content);
+        assert!(mailbox.receive(&MessageTemplate::any().performative(Performative::Request)).is_none());
+        assert_eq!(1, mailbox.len());
+    }
+
+    fn prioritized(priority: u8, text: u8) -> Message<Text> {
+        let mut message = message(Performative::Inform, ALICE, text);
+        message.priority = priority;
+        message
+    }
+
+    fn texts(messages: Vec<Message<Text>>) -> Vec<u8> {
+        messages.into_iter().map(|m| m.content.0).collect()
+    }
+
+    #[test]
+    fn it_should_take_the_messages_by_priority_then_by_arrival() {
+        let mut mailbox = Mailbox::new();
+        for &(priority, text) in &[(0, 0), (2, 1), (1, 2), (2, 3), (0, 4)] {
+            mailbox.push(prioritized(priority, text));
+        }
+
+        assert_eq!(Some(&Text(1)), mailbox.peek(&MessageTemplate::any()).map(|m| &m.content));
+        assert_eq!(vec![1, 3, 2, 0, 4], texts(mailbox.receive_all(&MessageTemplate::any())));
+    }
+
+    #[test]
+    fn it_should_raise_the_priority_of_waiting_messages_with_aging() {
+        let mut mailbox = Mailbox::with_aging(2);
+        mailbox.push(prioritized(0, 0));
+
+        let mut received = Vec::new();
+        for text in 1..4 {
+            mailbox.push(prioritized(1, text));
+            received.extend(mailbox.receive(&MessageTemplate::any()).map(|m| m.content.0));
+        }
+
+        assert_eq!(vec![1, 2, 0], received);
+        assert_eq!(1, mailbox.len());
+    }
+}
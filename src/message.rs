@@ -1,4 +1,8 @@
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    ops::Add,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use bincode;
 use uuid::Uuid;
@@ -6,8 +10,46 @@ use serde::{Serialize, de::DeserializeOwned};
 
 use agent::AgentId;
 use agent_system::SystemId;
+use conversation::InteractionProtocol;
 
-pub type Id = u8;
+/// Identifier of a conversation or of a message expecting a reply.
+pub type Id = Uuid;
+
+/// Generate a new conversation or reply identifier.
+pub fn new_id() -> Id {
+    Uuid::new_v4()
+}
+
+/// Date in milliseconds since the UNIX epoch.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+    pub fn now() -> Self {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::new(0, 0));
+
+        Timestamp(elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis()))
+    }
+
+    /// Time left before this deadline, `None` if it's over.
+    pub fn remaining(&self, now: Timestamp) -> Option<Duration> {
+        if self.0 > now.0 {
+            Some(Duration::from_millis(self.0 - now.0))
+        } else {
+            None
+        }
+    }
+}
+
+impl Add<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, duration: Duration) -> Timestamp {
+        Timestamp(self.0 + duration.as_secs() * 1000 + u64::from(duration.subsec_millis()))
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
 pub enum Recipient {
@@ -15,6 +57,12 @@ pub enum Recipient {
     Broadcast { system_id: Option<SystemId> },
 }
 
+impl From<(SystemId, AgentId)> for Recipient {
+    fn from((system_id, agent_id): (SystemId, AgentId)) -> Self {
+        Recipient::Agent { system_id, agent_id }
+    }
+}
+
 
 #[repr(u8)]
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
@@ -91,6 +139,9 @@ pub struct Message<C> {
     /// ontology used to give a meaning to the symbols in the content expression
     pub ontology: u8,
 
+    /// Interaction protocol followed by the conversation of the message.
+    pub protocol: Option<InteractionProtocol>,
+
     /// Set the priority level of the message.
     pub priority: u8,
 
@@ -108,7 +159,7 @@ pub struct Message<C> {
 
     /// Denotes a time and/or date expression which indicates the latest
     /// time by which the sending agent would like to receive a reply.
-    pub reply_by: Option<Id>,
+    pub reply_by: Option<Timestamp>,
 
     pub occurred: u64,
 
@@ -118,31 +169,13 @@ pub struct Message<C> {
 
 
 impl<C: Content> Message<C> {
-    pub fn new(
-        performative: Performative,
-        recipient: Recipient,
-        ontology: u8,
-        priority: u8,
-        conversation_id: Option<Id>,
-        reply_with: Option<Id>,
-        in_reply_to: Option<Id>,
-        reply_by: Option<Id>,
-        content: C,
-    ) -> Self {
-        Self {
-            id: Uuid::new_v4(),
-            sender: (0, 0), // temp value
-            recipient,
-            performative,
-            ontology,
-            priority,
-            conversation_id,
-            reply_with,
-            in_reply_to,
-            reply_by,
-            content,
-            occurred: 0,
-        }
+    /// Reply to the sender of this message, referencing it with `in_reply_to`
+    /// and continuing its conversation.
+    pub fn create_reply(&self, performative: Performative, content: C) -> Message<C> {
+        Message::reply_to(self)
+            .performative(performative)
+            .content(content)
+            .build()
     }
 
     pub fn serialize(&self) -> Result<Vec<u8>, Box<bincode::ErrorKind>> {
@@ -162,6 +195,172 @@ impl<C: Content> Message<C> {
     }
 }
 
+/// Content of a `MessageBuilder` which hasn't received its content yet.
+/// A message can't be built without a content.
+pub struct NoContent;
+
+/// Build a message field by field, the fields not set keep their default value.
+pub struct MessageBuilder<C> {
+    performative: Performative,
+    recipient: Recipient,
+    ontology: u8,
+    protocol: Option<InteractionProtocol>,
+    priority: u8,
+    conversation_id: Option<Id>,
+    reply_with: Option<Id>,
+    in_reply_to: Option<Id>,
+    reply_by: Option<Timestamp>,
+    content: C,
+}
+
+impl Message<NoContent> {
+    pub fn builder<R: Into<Recipient>>(performative: Performative, recipient: R) -> MessageBuilder<NoContent> {
+        MessageBuilder {
+            performative,
+            recipient: recipient.into(),
+            ontology: 0,
+            protocol: None,
+            priority: 0,
+            conversation_id: None,
+            reply_with: None,
+            in_reply_to: None,
+            reply_by: None,
+            content: NoContent,
+        }
+    }
+
+    pub fn inform<R: Into<Recipient>>(recipient: R) -> MessageBuilder<NoContent> {
+        Message::builder(Performative::Inform, recipient)
+    }
+
+    pub fn request<R: Into<Recipient>>(recipient: R) -> MessageBuilder<NoContent> {
+        Message::builder(Performative::Request, recipient)
+    }
+
+    pub fn query_if<R: Into<Recipient>>(recipient: R) -> MessageBuilder<NoContent> {
+        Message::builder(Performative::QueryIf, recipient)
+    }
+
+    pub fn query_ref<R: Into<Recipient>>(recipient: R) -> MessageBuilder<NoContent> {
+        Message::builder(Performative::QueryRef, recipient)
+    }
+
+    pub fn call_for_proposal<R: Into<Recipient>>(recipient: R) -> MessageBuilder<NoContent> {
+        Message::builder(Performative::CallForProposal, recipient)
+    }
+
+    pub fn propose<R: Into<Recipient>>(recipient: R) -> MessageBuilder<NoContent> {
+        Message::builder(Performative::Propose, recipient)
+    }
+
+    pub fn subscribe<R: Into<Recipient>>(recipient: R) -> MessageBuilder<NoContent> {
+        Message::builder(Performative::Subscribe, recipient)
+    }
+
+    /// Start a reply to the sender of the message, in the same conversation, protocol and ontology.
+    /// The reply is an `Inform` unless another performative is set.
+    pub fn reply_to<C>(message: &Message<C>) -> MessageBuilder<NoContent> {
+        let mut builder = Message::builder(Performative::Inform, message.sender);
+        builder.ontology = message.ontology;
+        builder.protocol = message.protocol;
+        builder.conversation_id = message.conversation_id;
+        builder.in_reply_to = message.reply_with;
+        builder
+    }
+}
+
+impl<C> MessageBuilder<C> {
+    pub fn performative(mut self, performative: Performative) -> Self {
+        self.performative = performative;
+        self
+    }
+
+    pub fn ontology(mut self, ontology: u8) -> Self {
+        self.ontology = ontology;
+        self
+    }
+
+    pub fn protocol(mut self, protocol: InteractionProtocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn conversation(mut self, conversation_id: Id) -> Self {
+        self.conversation_id = Some(conversation_id);
+        self
+    }
+
+    /// Open a new conversation with a generated identifier.
+    pub fn start_conversation(self) -> Self {
+        self.conversation(new_id())
+    }
+
+    pub fn reply_with(mut self, reply_with: Id) -> Self {
+        self.reply_with = Some(reply_with);
+        self
+    }
+
+    /// Ask for a reply referencing a generated identifier.
+    pub fn expect_reply(self) -> Self {
+        self.reply_with(new_id())
+    }
+
+    pub fn in_reply_to(mut self, in_reply_to: Id) -> Self {
+        self.in_reply_to = Some(in_reply_to);
+        self
+    }
+
+    pub fn reply_by(mut self, reply_by: Timestamp) -> Self {
+        self.reply_by = Some(reply_by);
+        self
+    }
+
+    /// Expect a reply before this delay from now.
+    pub fn reply_within(self, delay: Duration) -> Self {
+        self.reply_by(Timestamp::now() + delay)
+    }
+
+    pub fn content<T: Content>(self, content: T) -> MessageBuilder<T> {
+        MessageBuilder {
+            performative: self.performative,
+            recipient: self.recipient,
+            ontology: self.ontology,
+            protocol: self.protocol,
+            priority: self.priority,
+            conversation_id: self.conversation_id,
+            reply_with: self.reply_with,
+            in_reply_to: self.in_reply_to,
+            reply_by: self.reply_by,
+            content,
+        }
+    }
+}
+
+impl<C: Content> MessageBuilder<C> {
+    pub fn build(self) -> Message<C> {
+        Message {
+            id: Uuid::new_v4(),
+            sender: (0, AgentId::default()), // temp value
+            recipient: self.recipient,
+            performative: self.performative,
+            ontology: self.ontology,
+            protocol: self.protocol,
+            priority: self.priority,
+            conversation_id: self.conversation_id,
+            reply_with: self.reply_with,
+            in_reply_to: self.in_reply_to,
+            reply_by: self.reply_by,
+            content: self.content,
+            occurred: 0,
+        }
+    }
+}
+
 impl <C: Content>Ord for Message<C> {
     fn cmp(&self, other: &Message<C>) -> Ordering {
         self.priority.cmp(&other.priority)
@@ -202,29 +401,15 @@ mod test_message {
 
         assert!(high_priority > low_priority);
 
-        let message_with_high_priority = Message::new(
-            Performative::Confirm,
-            Recipient::Broadcast{ system_id: None },
-            0,
-            high_priority,
-            None,
-            None,
-            None,
-            None,
-            Position{ x: 23, y: 12 },
-        );
-
-        let message_with_low_priority = Message::new(
-            Performative::Confirm,
-            Recipient::Broadcast{ system_id: None },
-            0,
-            low_priority,
-            None,
-            None,
-            None,
-            None,
-            Position{ x: 23, y: 12 },
-        );
+        let message_with_high_priority = Message::builder(Performative::Confirm, Recipient::Broadcast{ system_id: None })
+            .priority(high_priority)
+            .content(Position{ x: 23, y: 12 })
+            .build();
+
+        let message_with_low_priority = Message::builder(Performative::Confirm, Recipient::Broadcast{ system_id: None })
+            .priority(low_priority)
+            .content(Position{ x: 23, y: 12 })
+            .build();
 
         assert_eq!(Ordering::Greater, message_with_high_priority.cmp(&message_with_low_priority));
         assert_eq!(Ordering::Less, message_with_low_priority.cmp(&message_with_high_priority));
@@ -234,21 +419,85 @@ mod test_message {
     fn it_should_serde_message() {
         let position = Position{ x: 23, y: 12 };
 
-        let message = Message::new(
-            Performative::Confirm,
-            Recipient::Broadcast{ system_id: None },
-            0,
-            0,
-            None,
-            None,
-            None,
-            None,
-            position,
-        );
+        let message = Message::builder(Performative::Confirm, Recipient::Broadcast{ system_id: None })
+            .content(position)
+            .build();
 
         let msg_ser = message.serialize().expect("Should be serialize");
         let msg_deser = Message::deserialize(&msg_ser).expect("Should be deserialize");;
 
         assert_eq!(message, msg_deser);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn it_should_build_a_message_from_the_fields_set() {
+        let conversation_id = new_id();
+        let reply_with = new_id();
+        let reply_by = Timestamp(1000);
+
+        let message = Message::request((1, AgentId::new(4, 2)))
+            .priority(3)
+            .conversation(conversation_id)
+            .reply_with(reply_with)
+            .reply_by(reply_by)
+            .content(Position{ x: 23, y: 12 })
+            .build();
+
+        assert_eq!(Performative::Request, message.performative);
+        assert_eq!(Recipient::Agent{ system_id: 1, agent_id: AgentId::new(4, 2) }, message.recipient);
+        assert_eq!(0, message.ontology);
+        assert_eq!(3, message.priority);
+        assert_eq!(Some(conversation_id), message.conversation_id);
+        assert_eq!(Some(reply_with), message.reply_with);
+        assert_eq!(None, message.in_reply_to);
+        assert_eq!(Some(reply_by), message.reply_by);
+        assert_eq!(Position{ x: 23, y: 12 }, message.content);
+    }
+
+    #[test]
+    fn it_should_reply_to_the_sender_of_a_message_in_the_same_conversation() {
+        let mut incoming = Message::query_ref(Recipient::Broadcast{ system_id: None })
+            .ontology(2)
+            .start_conversation()
+            .expect_reply()
+            .content(Position{ x: 23, y: 12 })
+            .build();
+        incoming.set_sender((1, AgentId::new(4, 2)));
+
+        let reply = Message::reply_to(&incoming)
+            .performative(Performative::Refuse)
+            .content(Position{ x: 0, y: 0 })
+            .build();
+
+        assert_eq!(Performative::Refuse, reply.performative);
+        assert_eq!(Recipient::Agent{ system_id: 1, agent_id: AgentId::new(4, 2) }, reply.recipient);
+        assert_eq!(2, reply.ontology);
+        assert!(reply.conversation_id.is_some());
+        assert_eq!(incoming.conversation_id, reply.conversation_id);
+        assert!(reply.in_reply_to.is_some());
+        assert_eq!(incoming.reply_with, reply.in_reply_to);
+        assert_eq!(None, reply.reply_with);
+    }
+
+    #[test]
+    fn it_should_generate_distinct_identifiers() {
+        let message = Message::inform(Recipient::Broadcast{ system_id: None })
+            .start_conversation()
+            .expect_reply()
+            .content(Position{ x: 23, y: 12 })
+            .build();
+
+        assert_ne!(message.conversation_id, message.reply_with);
+        assert_ne!(new_id(), new_id());
+    }
+
+    #[test]
+    fn it_should_compute_the_time_left_before_a_deadline() {
+        let now = Timestamp(1000);
+        let deadline = now + Duration::from_millis(1500);
+
+        assert_eq!(Timestamp(2500), deadline);
+        assert_eq!(Some(Duration::from_millis(1500)), deadline.remaining(now));
+        assert_eq!(None, now.remaining(deadline));
+    }
+}
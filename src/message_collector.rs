@@ -1,6 +1,7 @@
 use zmq::{Socket, Context as ZmqContext, SUB, PollItem, POLLIN, poll as zmq_poll, Message as ZmqMessage};
 
 use message::*;
+use control::{Control, Packet};
 
 use std::{
     sync::mpsc::Receiver,
@@ -15,20 +16,22 @@ const INBOX_CAPACITY: usize = 128;
 pub struct Collector<C: Content> {
     system_id: u8,
     zmq_ctx: ZmqContext,
-    local_collector: Receiver<Message<C>>,
+    local_collector: Receiver<Packet<C>>,
     remotes_collector: Vec<Socket>,
     inbox: VecDeque<Message<C>>,
+    controls: VecDeque<Control>,
 }
 
 pub const SEND_TO_AGENT: u8 = 0;
 pub const BROADCAST_TO_SYSTEM: u8 = 1;
 pub const BROADCAST_TO_ALL: u8 = 2;
+pub const SYSTEM_CONTROL: u8 = 3;
 
 impl <C: Content>Collector<C> {
 
     pub fn new(system_id: u8,
         zmq_ctx: ZmqContext,
-        local_collector: Receiver<Message<C>>,
+        local_collector: Receiver<Packet<C>>,
         inbox_capacity: Option<usize>,
     ) -> Self {
         Collector {
@@ -37,6 +40,7 @@ impl <C: Content>Collector<C> {
             local_collector,
             remotes_collector: Vec::new(),
             inbox: VecDeque::with_capacity(inbox_capacity.unwrap_or(INBOX_CAPACITY)),
+            controls: VecDeque::new(),
         }
     }
 
@@ -51,6 +55,9 @@ impl <C: Content>Collector<C> {
                     .and_then(|_| {
                         zmq_subscriber.set_subscribe(&[BROADCAST_TO_ALL])
                     })
+                    .and_then(|_| {
+                        zmq_subscriber.set_subscribe(&[SYSTEM_CONTROL, self.system_id])
+                    })
                     .unwrap_or_else(|_| {
                         error!("Can't set message filters for the system: {}", self.system_id);
                     });
@@ -69,6 +76,10 @@ impl <C: Content>Collector<C> {
         None
     }
 
+    pub fn drain_controls(&mut self) -> Vec<Control> {
+        self.controls.drain(..).collect()
+    }
+
     pub fn collect_messages(&mut self) {
         self.collect_remotes_message();
         self.collect_local_message();
@@ -85,7 +96,13 @@ impl <C: Content>Collector<C> {
         for (index_collector, socket) in sockets_to_poll.iter().enumerate() {
             if socket.is_readable() {
                 while let Ok(msg) = self.remotes_collector[index_collector].recv_multipart(NO_FLAGS) {
-                    if self.inbox.len() < self.inbox.capacity() {
+                    if msg[0].first() == Some(&SYSTEM_CONTROL) {
+                        if let Ok(control) = Control::deserialize(&msg[1]) {
+                            self.controls.push_back(control);
+                        } else {
+                            trace!("Receive a control message that can't be deserialize");
+                        }
+                    } else if self.inbox.len() < self.inbox.capacity() {
                         if let Ok(message) = Message::<C>::deserialize(&msg[1]) {
                             self.inbox.push_back(message);
                         } else {
@@ -101,8 +118,11 @@ impl <C: Content>Collector<C> {
     }
 
     fn collect_local_message(&mut self) {
-        for message in self.local_collector.try_iter() {
-            self.inbox.push_back(message);
+        for packet in self.local_collector.try_iter() {
+            match packet {
+                Packet::Agent(message) => self.inbox.push_back(message),
+                Packet::Control(control) => self.controls.push_back(control),
+            }
 
             if self.inbox.capacity() == 0 {
                 break;
@@ -0,0 +1,604 @@
+use std::{
+    collections::HashMap,
+    time::Duration,
+};
+
+use agent::AgentId;
+use agent_system::SystemId;
+use conversation::InteractionProtocol;
+use message::{Content, Id, Message, Performative, Timestamp, new_id};
+
+/// Content of the messages of an auction, which carry the prices called and bid.
+pub trait AuctionContent: Content {
+    fn from_price(price: u64) -> Self;
+
+    fn price(&self) -> Option<u64>;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AuctionKind {
+    /// The price rises each round while some bidders outbid the leader.
+    English,
+    /// The price falls each round until a bidder accepts it.
+    Dutch,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AuctionRules {
+    pub start_price: u64,
+    /// Raise of the price in an English auction, drop in a Dutch one.
+    pub step: u64,
+    /// The item isn't sold below this price.
+    pub reserve_price: u64,
+    /// Time left to the bidders to answer a call, bidders not bidding in time pass on the round.
+    pub round_duration: Duration,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AuctionState {
+    Running,
+    Sold,
+    Unsold,
+}
+
+/// Auctioneer role: calls the bidders at the price of each round, rejecting the outbid ones,
+/// then accepts the winning bid and informs every bidder of the end of the auction.
+/// A Dutch auction starting below its reserve price is withdrawn right away.
+pub struct Auctioneer<C> {
+    kind: AuctionKind,
+    rules: AuctionRules,
+    conversation_id: Id,
+    participants: Vec<(SystemId, AgentId)>,
+    state: AuctionState,
+    price: u64,
+    deadline: Timestamp,
+    bids: Vec<Message<C>>,
+    winner: Option<Message<C>>,
+    outbox: Vec<Message<C>>,
+}
+
+impl<C: AuctionContent> Auctioneer<C> {
+    pub fn new(kind: AuctionKind, participants: Vec<(SystemId, AgentId)>, rules: AuctionRules, now: Timestamp) -> Self {
+        let mut auctioneer = Auctioneer {
+            kind,
+            rules,
+            conversation_id: new_id(),
+            participants,
+            state: AuctionState::Running,
+            price: rules.start_price,
+            deadline: now,
+            bids: Vec::new(),
+            winner: None,
+            outbox: Vec::new(),
+        };
+
+        if kind == AuctionKind::Dutch && rules.start_price < rules.reserve_price {
+            warn!("The Dutch auction {} starts below its reserve price", auctioneer.conversation_id);
+            auctioneer.state = AuctionState::Unsold;
+        } else {
+            auctioneer.call(now);
+        }
+        auctioneer
+    }
+
+    pub fn english(participants: Vec<(SystemId, AgentId)>, rules: AuctionRules, now: Timestamp) -> Self {
+        Auctioneer::new(AuctionKind::English, participants, rules, now)
+    }
+
+    pub fn dutch(participants: Vec<(SystemId, AgentId)>, rules: AuctionRules, now: Timestamp) -> Self {
+        Auctioneer::new(AuctionKind::Dutch, participants, rules, now)
+    }
+
+    fn protocol(&self) -> InteractionProtocol {
+        match self.kind {
+            AuctionKind::English => InteractionProtocol::EnglishAuction,
+            AuctionKind::Dutch => InteractionProtocol::DutchAuction,
+        }
+    }
+
+    /// Start a round calling every bidder at the current price.
+    /// The calls have no `reply_by`, as a bidder can pass on a round without answering.
+    fn call(&mut self, now: Timestamp) {
+        self.deadline = now + self.rules.round_duration;
+
+        for &participant in self.participants.iter() {
+            self.outbox.push(Message::call_for_proposal(participant)
+                .protocol(self.protocol())
+                .conversation(self.conversation_id)
+                .expect_reply()
+                .content(C::from_price(self.price))
+                .build());
+        }
+    }
+
+    fn reject(&mut self, bid: &Message<C>, price: u64) {
+        self.outbox.push(bid.create_reply(Performative::RejectProposal, C::from_price(price)));
+    }
+
+    pub fn conversation_id(&self) -> Id {
+        self.conversation_id
+    }
+
+    pub fn state(&self) -> AuctionState {
+        self.state
+    }
+
+    /// Price called in the current round, or the price at which the item has been sold.
+    pub fn price(&self) -> u64 {
+        self.price
+    }
+
+    /// Bid accepted at the end of the auction, or leading an English auction.
+    pub fn winner(&self) -> Option<&Message<C>> {
+        self.winner.as_ref()
+    }
+
+    /// Record a bid, returns false if the message isn't a valid bid for the current round.
+    pub fn handle_message(&mut self, message: &Message<C>) -> bool {
+        if self.state != AuctionState::Running
+            || message.performative != Performative::Propose
+            || message.conversation_id != Some(self.conversation_id)
+            || !self.participants.contains(&message.sender) {
+            return false;
+        }
+
+        let valid = match (self.kind, message.content.price()) {
+            (AuctionKind::English, Some(price)) => price >= self.price,
+            (AuctionKind::English, None) => false,
+            // A Dutch bid takes the item at the called price.
+            (AuctionKind::Dutch, Some(price)) => price == self.price,
+            (AuctionKind::Dutch, None) => true,
+        };
+
+        if valid {
+            self.bids.push(message.clone());
+        }
+        valid
+    }
+
+    /// Close the round if it's over, the winner of the round being the highest bid,
+    /// or the first one in a Dutch auction.
+    pub fn poll(&mut self, now: Timestamp) {
+        let kind = self.kind;
+        self.poll_with(now, |bids| match kind {
+            AuctionKind::English => bids.iter()
+                .enumerate()
+                .max_by_key(|&(index, bid)| (bid.content.price(), -(index as i64)))
+                .map(|(index, _)| index),
+            AuctionKind::Dutch => if bids.is_empty() { None } else { Some(0) },
+        });
+    }
+
+    /// Close the round if it's over, the winner of the round being the bid at the index
+    /// returned by `choose`, if any.
+    pub fn poll_with<F>(&mut self, now: Timestamp, choose: F)
+        where F: FnOnce(&[Message<C>]) -> Option<usize>
+    {
+        if self.state != AuctionState::Running {
+            return;
+        }
+
+        match self.kind {
+            AuctionKind::English if self.deadline <= now => {
+                let mut bids: Vec<Message<C>> = self.bids.drain(..).collect();
+
+                match choose(&bids).filter(|&index| index < bids.len()) {
+                    Some(index) => {
+                        let bid = bids.remove(index);
+                        self.price = bid.content.price().unwrap_or(self.price);
+
+                        // The leader which hasn't bid again in the round is outbid too.
+                        let leader = self.winner.take().filter(|leader| {
+                            leader.sender != bid.sender && bids.iter().all(|other| other.sender != leader.sender)
+                        });
+                        for outbid in bids.iter().chain(leader.iter()) {
+                            self.reject(outbid, self.price);
+                        }
+
+                        self.winner = Some(bid);
+                        self.price += self.rules.step;
+                        self.call(now);
+                    },
+                    None => {
+                        let sold = match self.winner {
+                            Some(ref bid) => bid.content.price().is_some_and(|price| price >= self.rules.reserve_price),
+                            None => false,
+                        };
+                        self.close(sold);
+                    },
+                }
+            },
+            AuctionKind::Dutch => {
+                if let Some(bid) = choose(&self.bids).and_then(|index| self.bids.get(index).cloned()) {
+                    self.winner = Some(bid);
+                    self.close(true);
+                } else if self.deadline <= now {
+                    self.bids.clear();
+
+                    match self.price.checked_sub(self.rules.step) {
+                        Some(price) if price >= self.rules.reserve_price => {
+                            self.price = price;
+                            self.call(now);
+                        },
+                        _ => self.close(false),
+                    }
+                }
+            },
+            AuctionKind::English => {},
+        }
+    }
+
+    /// Accept the winning bid, reject the others and inform every bidder of the final price,
+    /// the price of the winning bid in an English auction and the called price in a Dutch one.
+    fn close(&mut self, sold: bool) {
+        if sold {
+            if let Some(ref winner) = self.winner {
+                if self.kind == AuctionKind::English {
+                    self.price = winner.content.price().unwrap_or(self.price);
+                }
+                self.outbox.push(Message::reply_to(winner)
+                    .performative(Performative::AcceptProposal)
+                    .expect_reply()
+                    .content(C::from_price(self.price))
+                    .build());
+            }
+        } else if let Some(leader) = self.winner.take() {
+            // The leader of an English auction didn't reach the reserve price.
+            self.reject(&leader, self.price);
+        }
+
+        let bids: Vec<Message<C>> = self.bids.drain(..).collect();
+        for bid in bids {
+            if self.winner.as_ref().map(|winner| winner.id) != Some(bid.id) {
+                self.reject(&bid, self.price);
+            }
+        }
+
+        for &participant in self.participants.iter() {
+            self.outbox.push(Message::inform(participant)
+                .protocol(self.protocol())
+                .conversation(self.conversation_id)
+                .content(C::from_price(self.price))
+                .build());
+        }
+
+        self.state = if sold { AuctionState::Sold } else { AuctionState::Unsold };
+    }
+
+    /// Messages to send, to return from `act`.
+    pub fn take_messages(&mut self) -> Vec<Message<C>> {
+        self.outbox.drain(..).collect()
+    }
+}
+
+/// Step of an auction reached by a message received by a bidder.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BidderEvent {
+    /// The auctioneer calls for bids at this price, the agent can `bid`.
+    Call { price: u64 },
+    /// The bid of the agent won the auction at this price.
+    Won { price: u64 },
+    /// The bid of the agent lost, outbid in an English auction where the agent can still bid
+    /// on the next call.
+    Lost,
+    /// The auction is over, sold or not, at this price.
+    Closed { price: u64 },
+}
+
+/// Bidder role of the English and Dutch auctions, following the auctions from their first call
+/// to the `Inform` closing them.
+pub struct Bidder<C> {
+    auctions: HashMap<Id, u64>,
+    outbox: Vec<Message<C>>,
+}
+
+impl<C: AuctionContent> Bidder<C> {
+    pub fn new() -> Self {
+        Bidder {
+            auctions: HashMap::new(),
+            outbox: Vec::new(),
+        }
+    }
+
+    /// Tell the agent what happened in the auction, `None` if the message isn't part of an auction.
+    pub fn handle_message(&mut self, message: &Message<C>) -> Option<BidderEvent> {
+        match message.protocol {
+            Some(InteractionProtocol::EnglishAuction) | Some(InteractionProtocol::DutchAuction) => {},
+            _ => return None,
+        }
+
+        let conversation_id = message.conversation_id?;
+        let price = message.content.price();
+
+        match (&message.performative, price) {
+            (&Performative::CallForProposal, Some(price)) => {
+                self.auctions.insert(conversation_id, price);
+                Some(BidderEvent::Call { price })
+            },
+            (&Performative::AcceptProposal, Some(price)) => Some(BidderEvent::Won { price }),
+            (&Performative::RejectProposal, _) => Some(BidderEvent::Lost),
+            (&Performative::Inform, price) => {
+                self.auctions.remove(&conversation_id);
+                price.map(|price| BidderEvent::Closed { price })
+            },
+            _ => None,
+        }
+    }
+
+    /// Bid on the call at this price. In a Dutch auction, bidding accepts the price called.
+    pub fn bid(&mut self, call: &Message<C>, price: u64) {
+        match call.conversation_id {
+            Some(id) if self.auctions.contains_key(&id) => {
+                self.outbox.push(Message::reply_to(call)
+                    .performative(Performative::Propose)
+                    .expect_reply()
+                    .content(C::from_price(price))
+                    .build());
+            },
+            _ => warn!("Can't bid on the message {} which isn't an open auction", call.id),
+        }
+    }
+
+    /// Stop following an auction, the agent won't be able to bid on it anymore.
+    pub fn forget(&mut self, conversation_id: Id) {
+        self.auctions.remove(&conversation_id);
+    }
+
+    /// Number of auctions followed by the bidder, which haven't been closed yet.
+    pub fn nb_auctions(&self) -> usize {
+        self.auctions.len()
+    }
+
+    /// Messages to send, to return from `act`.
+    pub fn take_messages(&mut self) -> Vec<Message<C>> {
+        self.outbox.drain(..).collect()
+    }
+}
+
+impl<C: AuctionContent> Default for Bidder<C> {
+    fn default() -> Self {
+        Bidder::new()
+    }
+}
+
+#[cfg(test)]
+mod test_auction {
+
+    use super::*;
+    use message::Recipient;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Price(u64);
+
+    impl Content for Price {}
+
+    impl AuctionContent for Price {
+        fn from_price(price: u64) -> Self { Price(price) }
+
+        fn price(&self) -> Option<u64> { Some(self.0) }
+    }
+
+    const AUCTIONEER: (SystemId, AgentId) = (0, AgentId { index: 0, generation: 0 });
+
+    fn bidder_id(index: usize) -> (SystemId, AgentId) {
+        (0, AgentId::new(index, 0))
+    }
+
+    fn rules(reserve_price: u64) -> AuctionRules {
+        AuctionRules {
+            start_price: 100,
+            step: 10,
+            reserve_price,
+            round_duration: Duration::from_secs(1),
+        }
+    }
+
+    fn sent_by(mut messages: Vec<Message<Price>>, sender: (SystemId, AgentId)) -> Vec<Message<Price>> {
+        for m in messages.iter_mut() {
+            m.set_sender(sender);
+        }
+        messages
+    }
+
+    /// Let the bidders bid on the calls sent to them, and give their bids to the auctioneer.
+    /// Returns the other messages sent by the auctioneer.
+    fn round(auctioneer: &mut Auctioneer<Price>, bidders: &mut [(Bidder<Price>, Option<u64>)]) -> Vec<Message<Price>> {
+        let (calls, others): (Vec<Message<Price>>, Vec<Message<Price>>) = sent_by(auctioneer.take_messages(), AUCTIONEER)
+            .into_iter()
+            .partition(|m| m.performative == Performative::CallForProposal);
+
+        for (index, &mut (ref mut bidder, max_price)) in bidders.iter_mut().enumerate() {
+            let call = &calls[index];
+            let price = match bidder.handle_message(call) {
+                Some(BidderEvent::Call { price }) => price,
+                event => panic!("Should be called, not {:?}", event),
+            };
+
+            if max_price.is_some_and(|max_price| price <= max_price) {
+                bidder.bid(call, price);
+            }
+
+            for bid in sent_by(bidder.take_messages(), bidder_id(index + 1)) {
+                assert!(auctioneer.handle_message(&bid));
+            }
+        }
+
+        others
+    }
+
+    fn recipient(index: usize) -> Recipient {
+        Recipient::Agent { system_id: 0, agent_id: AgentId::new(index, 0) }
+    }
+
+    #[test]
+    fn it_should_sell_to_the_last_bidder_of_an_english_auction() {
+        let mut now = Timestamp(0);
+        let mut auctioneer = Auctioneer::english(vec![bidder_id(1), bidder_id(2)], rules(100), now);
+        let mut bidders = vec![(Bidder::new(), Some(110)), (Bidder::new(), Some(120))];
+
+        while auctioneer.state() == AuctionState::Running {
+            round(&mut auctioneer, &mut bidders);
+            now = now + Duration::from_secs(1);
+            auctioneer.poll(now);
+        }
+
+        assert_eq!(AuctionState::Sold, auctioneer.state());
+        assert_eq!(120, auctioneer.price());
+        assert_eq!(bidder_id(2), auctioneer.winner().expect("Should have a winner").sender);
+
+        let messages = auctioneer.take_messages();
+        assert_eq!(Performative::AcceptProposal, messages[0].performative);
+        assert_eq!(Recipient::Agent { system_id: 0, agent_id: AgentId::new(2, 0) }, messages[0].recipient);
+        assert!(messages[1..].iter().all(|m| m.performative == Performative::Inform && m.content == Price(120)));
+    }
+
+    #[test]
+    fn it_should_reject_the_outbid_bidders_of_an_english_auction() {
+        let mut auctioneer = Auctioneer::english(vec![bidder_id(1), bidder_id(2)], rules(100), Timestamp(0));
+        let mut bidders = vec![(Bidder::new(), Some(110)), (Bidder::new(), Some(120))];
+
+        // Both bid 100, the first bid leads.
+        round(&mut auctioneer, &mut bidders);
+        auctioneer.poll(Timestamp(1000));
+        let rejected = round(&mut auctioneer, &mut bidders);
+
+        assert_eq!(1, rejected.len());
+        assert_eq!(Performative::RejectProposal, rejected[0].performative);
+        assert_eq!(recipient(2), rejected[0].recipient);
+        assert_eq!(Some(BidderEvent::Lost), bidders[1].0.handle_message(&rejected[0]));
+
+        // The second bidder alone bids 120, outbidding the leader.
+        auctioneer.poll(Timestamp(2000));
+        round(&mut auctioneer, &mut bidders);
+        auctioneer.poll(Timestamp(3000));
+        let rejected = round(&mut auctioneer, &mut bidders);
+
+        assert_eq!(1, rejected.len());
+        assert_eq!(recipient(1), rejected[0].recipient);
+        assert_eq!(Price(120), rejected[0].content);
+    }
+
+    #[test]
+    fn it_should_close_an_auction_without_bids() {
+        let mut auctioneer = Auctioneer::english(vec![bidder_id(1), bidder_id(2)], rules(0), Timestamp(0));
+        let mut bidders: Vec<Bidder<Price>> = vec![Bidder::new(), Bidder::new()];
+
+        let calls = auctioneer.take_messages();
+        assert!(calls.iter().all(|call| call.reply_by.is_none()));
+        for (index, call) in sent_by(calls.clone(), AUCTIONEER).iter().enumerate() {
+            bidders[index].handle_message(call);
+        }
+        auctioneer.poll(Timestamp(1000));
+
+        assert_eq!(AuctionState::Unsold, auctioneer.state());
+        assert!(auctioneer.winner().is_none());
+
+        let informs = sent_by(auctioneer.take_messages(), AUCTIONEER);
+        assert_eq!(2, informs.len());
+        assert!(informs.iter().all(|m| m.performative == Performative::Inform));
+
+        let bidder = &mut bidders[0];
+        assert_eq!(Some(BidderEvent::Closed { price: 100 }), bidder.handle_message(&informs[0]));
+        assert_eq!(0, bidder.nb_auctions());
+        bidder.bid(&calls[0], 100);
+        assert!(bidder.take_messages().is_empty());
+    }
+
+    #[test]
+    fn it_should_not_sell_below_the_reserve_price() {
+        let mut auctioneer = Auctioneer::english(vec![bidder_id(1)], rules(150), Timestamp(0));
+        let mut bidders = vec![(Bidder::new(), Some(100))];
+
+        round(&mut auctioneer, &mut bidders);
+        auctioneer.poll(Timestamp(1000));
+        round(&mut auctioneer, &mut vec![(Bidder::new(), None)]);
+        auctioneer.poll(Timestamp(2000));
+
+        assert_eq!(AuctionState::Unsold, auctioneer.state());
+        assert!(auctioneer.winner().is_none());
+
+        let messages = auctioneer.take_messages();
+        assert_eq!(Performative::RejectProposal, messages[0].performative);
+        assert_eq!(recipient(1), messages[0].recipient);
+    }
+
+    #[test]
+    fn it_should_withdraw_a_dutch_auction_starting_below_the_reserve_price() {
+        let mut auctioneer: Auctioneer<Price> = Auctioneer::dutch(vec![bidder_id(1)], rules(150), Timestamp(0));
+
+        assert_eq!(AuctionState::Unsold, auctioneer.state());
+        assert!(auctioneer.take_messages().is_empty());
+    }
+
+    #[test]
+    fn it_should_reject_a_bid_below_the_price_of_an_english_round() {
+        let mut auctioneer = Auctioneer::english(vec![bidder_id(1)], rules(0), Timestamp(0));
+        let call = sent_by(auctioneer.take_messages(), AUCTIONEER).remove(0);
+        let mut bid = call.create_reply(Performative::Propose, Price(90));
+        bid.set_sender(bidder_id(1));
+
+        assert!(!auctioneer.handle_message(&bid));
+    }
+
+    #[test]
+    fn it_should_reject_a_bid_under_the_price_of_a_dutch_round() {
+        let mut auctioneer = Auctioneer::dutch(vec![bidder_id(1)], rules(50), Timestamp(0));
+        let call = sent_by(auctioneer.take_messages(), AUCTIONEER).remove(0);
+        let mut bid = call.create_reply(Performative::Propose, Price(10));
+        bid.set_sender(bidder_id(1));
+
+        assert!(!auctioneer.handle_message(&bid));
+        auctioneer.poll(Timestamp(0));
+        assert_eq!(AuctionState::Running, auctioneer.state());
+        assert_eq!(100, auctioneer.price());
+    }
+
+    #[test]
+    fn it_should_lower_the_price_of_a_dutch_auction_until_a_bid() {
+        let mut auctioneer = Auctioneer::dutch(vec![bidder_id(1), bidder_id(2)], rules(50), Timestamp(0));
+        let mut bidders = vec![(Bidder::new(), Some(75)), (Bidder::new(), Some(85))];
+
+        round(&mut auctioneer, &mut bidders);
+        auctioneer.poll(Timestamp(1000));
+        assert_eq!(90, auctioneer.price());
+
+        round(&mut auctioneer, &mut bidders);
+        auctioneer.poll(Timestamp(2000));
+        assert_eq!(AuctionState::Running, auctioneer.state());
+
+        round(&mut auctioneer, &mut bidders);
+        auctioneer.poll(Timestamp(2500));
+
+        assert_eq!(AuctionState::Sold, auctioneer.state());
+        assert_eq!(80, auctioneer.price());
+        assert_eq!(bidder_id(2), auctioneer.winner().expect("Should have a winner").sender);
+    }
+
+    #[test]
+    fn it_should_let_the_auctioneer_choose_the_winner() {
+        let mut auctioneer = Auctioneer::dutch(vec![bidder_id(1), bidder_id(2)], rules(50), Timestamp(0));
+        let mut bidders = vec![(Bidder::new(), Some(100)), (Bidder::new(), Some(100))];
+
+        round(&mut auctioneer, &mut bidders);
+        auctioneer.poll_with(Timestamp(0), |bids| Some(bids.len() - 1));
+
+        assert_eq!(bidder_id(2), auctioneer.winner().expect("Should have a winner").sender);
+
+        let messages = auctioneer.take_messages();
+        assert_eq!(Performative::AcceptProposal, messages[0].performative);
+        assert_eq!(Performative::RejectProposal, messages[1].performative);
+    }
+
+    #[test]
+    fn it_should_withdraw_a_dutch_auction_reaching_the_reserve_price() {
+        let mut auctioneer = Auctioneer::dutch(vec![bidder_id(1)], rules(90), Timestamp(0));
+        let mut bidders = vec![(Bidder::new(), None)];
+
+        round(&mut auctioneer, &mut bidders);
+        auctioneer.poll(Timestamp(1000));
+        round(&mut auctioneer, &mut bidders);
+        auctioneer.poll(Timestamp(2000));
+
+        assert_eq!(AuctionState::Unsold, auctioneer.state());
+    }
+}
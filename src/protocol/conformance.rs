@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use agent::AgentId;
+use agent_system::SystemId;
+use conversation::InteractionProtocol;
+use message::{Id, Message, Performative, Recipient};
+
+/// What to do with a message breaking the protocol of its conversation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ViolationPolicy {
+    /// Record and log the violation, the message goes through.
+    Log,
+    /// Record and log the violation, the message is dropped.
+    Reject,
+}
+
+/// Step of a conversation between the agent and one of its peers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Step {
+    Start,
+    Requested,
+    Agreed,
+    Cancelling,
+    Called,
+    Proposed,
+    Accepted,
+    Subscribed,
+    Notifying,
+    Done,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Violation {
+    pub message_id: Uuid,
+    pub conversation_id: Id,
+    pub protocol: InteractionProtocol,
+    pub peer: (SystemId, AgentId),
+    pub step: Step,
+    pub performative: Performative,
+}
+
+/// Next step of a conversation following the protocol, `None` if the performative isn't allowed.
+fn next_step(protocol: InteractionProtocol, step: Step, performative: &Performative) -> Option<Step> {
+    use conversation::InteractionProtocol as P;
+    use message::Performative as A;
+
+    let next = match (protocol, step, performative) {
+        (_, Step::Done, _) | (_, Step::Start, &A::NotUnderstood) => return None,
+        (_, _, &A::NotUnderstood) => Step::Done,
+
+        (P::Request, Step::Start, &A::Request) => Step::Requested,
+        (P::Query, Step::Start, &A::QueryIf) | (P::Query, Step::Start, &A::QueryRef) => Step::Requested,
+        (P::Request, Step::Requested, &A::Agree) | (P::Query, Step::Requested, &A::Agree) => Step::Agreed,
+        (P::Request, Step::Requested, &A::Refuse) | (P::Query, Step::Requested, &A::Refuse) => Step::Done,
+        (P::Request, Step::Requested, &A::Inform) | (P::Request, Step::Requested, &A::Failure)
+            | (P::Request, Step::Agreed, &A::Inform) | (P::Request, Step::Agreed, &A::Failure)
+            | (P::Query, Step::Requested, &A::Inform) | (P::Query, Step::Requested, &A::Failure)
+            | (P::Query, Step::Agreed, &A::Inform) | (P::Query, Step::Agreed, &A::Failure) => Step::Done,
+        (P::Request, Step::Requested, &A::Cancel) | (P::Request, Step::Agreed, &A::Cancel)
+            | (P::Query, Step::Requested, &A::Cancel) | (P::Query, Step::Agreed, &A::Cancel) => Step::Cancelling,
+        // The request goes on when the participant fails to cancel it.
+        (P::Request, Step::Cancelling, &A::Failure) | (P::Query, Step::Cancelling, &A::Failure) => Step::Agreed,
+        (P::Request, Step::Cancelling, &A::Inform) | (P::Query, Step::Cancelling, &A::Inform) => Step::Done,
+
+        (P::ContractNet, Step::Start, &A::CallForProposal) => Step::Called,
+        (P::ContractNet, Step::Called, &A::Propose) => Step::Proposed,
+        (P::ContractNet, Step::Called, &A::Refuse) => Step::Done,
+        (P::ContractNet, Step::Proposed, &A::AcceptProposal) => Step::Accepted,
+        (P::ContractNet, Step::Proposed, &A::RejectProposal) => Step::Done,
+        (P::ContractNet, Step::Accepted, &A::Inform) | (P::ContractNet, Step::Accepted, &A::Failure) => Step::Done,
+
+        (P::Subscribe, Step::Start, &A::Subscribe) | (P::Subscribe, Step::Start, &A::RequestWhenever) => Step::Subscribed,
+        (P::Subscribe, Step::Subscribed, &A::Agree) => Step::Notifying,
+        (P::Subscribe, Step::Subscribed, &A::Refuse) => Step::Done,
+        (P::Subscribe, Step::Notifying, &A::Inform) => Step::Notifying,
+        (P::Subscribe, Step::Subscribed, &A::Cancel) | (P::Subscribe, Step::Notifying, &A::Cancel)
+            | (P::Subscribe, Step::Notifying, &A::Failure) => Step::Done,
+
+        (P::EnglishAuction, Step::Start, &A::CallForProposal) | (P::DutchAuction, Step::Start, &A::CallForProposal)
+            | (P::EnglishAuction, Step::Called, &A::CallForProposal) | (P::DutchAuction, Step::Called, &A::CallForProposal)
+            | (P::EnglishAuction, Step::Proposed, &A::CallForProposal) => Step::Called,
+        (P::EnglishAuction, Step::Called, &A::Propose) | (P::DutchAuction, Step::Called, &A::Propose) => Step::Proposed,
+        // The last round of an English auction calls the leader, who doesn't outbid itself.
+        (P::EnglishAuction, Step::Proposed, &A::AcceptProposal) | (P::EnglishAuction, Step::Called, &A::AcceptProposal)
+            | (P::DutchAuction, Step::Proposed, &A::AcceptProposal) => Step::Accepted,
+        // The leader of an English auction is rejected once outbid, after the call of the next round.
+        (P::EnglishAuction, Step::Proposed, &A::RejectProposal) | (P::EnglishAuction, Step::Called, &A::RejectProposal)
+            | (P::DutchAuction, Step::Proposed, &A::RejectProposal) => Step::Called,
+        (P::EnglishAuction, _, &A::Inform) | (P::DutchAuction, _, &A::Inform) => Step::Done,
+
+        _ => return None,
+    };
+
+    Some(next)
+}
+
+/// Number of violations a checker keeps, the oldest ones are dropped first.
+pub const MAX_VIOLATIONS: usize = 1000;
+
+/// Follow the conversations of an agent with each of its peers, and check the performative
+/// of every message sent or received against the protocol of the conversation.
+/// The protocol is the one declared for the conversation, or else the one of its first message.
+pub struct ConformanceChecker {
+    policy: ViolationPolicy,
+    protocols: HashMap<Id, InteractionProtocol>,
+    steps: HashMap<Id, HashMap<(SystemId, AgentId), Step>>,
+    violations: Vec<Violation>,
+}
+
+impl ConformanceChecker {
+    pub fn new(policy: ViolationPolicy) -> Self {
+        ConformanceChecker {
+            policy,
+            protocols: HashMap::new(),
+            steps: HashMap::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    pub fn declare(&mut self, conversation_id: Id, protocol: InteractionProtocol) {
+        self.protocols.insert(conversation_id, protocol);
+    }
+
+    /// Check a message sent by the agent, returns false if it must be dropped.
+    /// Broadcast messages aren't checked.
+    pub fn check_sent<C>(&mut self, message: &Message<C>) -> bool {
+        match message.recipient {
+            Recipient::Agent { system_id, agent_id } => self.check(message, (system_id, agent_id)),
+            Recipient::Broadcast { .. } => true,
+        }
+    }
+
+    /// Check a message received by the agent, returns false if it must be dropped.
+    pub fn check_received<C>(&mut self, message: &Message<C>) -> bool {
+        self.check(message, message.sender)
+    }
+
+    fn check<C>(&mut self, message: &Message<C>, peer: (SystemId, AgentId)) -> bool {
+        let conversation_id = match message.conversation_id {
+            Some(conversation_id) => conversation_id,
+            None => return true,
+        };
+
+        let protocol = match self.protocols.get(&conversation_id).cloned().or(message.protocol) {
+            Some(protocol) => protocol,
+            None => return true,
+        };
+        self.protocols.insert(conversation_id, protocol);
+
+        let step = self.step(conversation_id, peer);
+
+        match next_step(protocol, step, &message.performative) {
+            Some(next) => {
+                self.steps.entry(conversation_id).or_default().insert(peer, next);
+                true
+            },
+            None => {
+                warn!("{:?} isn't allowed at the step {:?} of the {:?} conversation {}",
+                      message.performative, step, protocol, conversation_id);
+                if self.violations.len() >= MAX_VIOLATIONS {
+                    let excess = self.violations.len() + 1 - MAX_VIOLATIONS;
+                    self.violations.drain(..excess);
+                }
+                self.violations.push(Violation {
+                    message_id: message.id,
+                    conversation_id,
+                    protocol,
+                    peer,
+                    step,
+                    performative: message.performative.clone(),
+                });
+                self.policy == ViolationPolicy::Log
+            },
+        }
+    }
+
+    /// Step of the conversation with this peer.
+    pub fn step(&self, conversation_id: Id, peer: (SystemId, AgentId)) -> Step {
+        self.steps.get(&conversation_id)
+            .and_then(|steps| steps.get(&peer))
+            .cloned()
+            .unwrap_or(Step::Start)
+    }
+
+    /// Whether the conversation is done with every peer it has been checked with.
+    pub fn is_over(&self, conversation_id: Id) -> bool {
+        self.steps.get(&conversation_id)
+            .is_some_and(|steps| steps.values().all(|&step| step == Step::Done))
+    }
+
+    /// Conversations followed by the checker.
+    pub fn conversations(&self) -> impl Iterator<Item=&Id> {
+        self.protocols.keys()
+    }
+
+    /// Last violations, at most `MAX_VIOLATIONS`.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    /// Remove and return the recorded violations.
+    pub fn take_violations(&mut self) -> Vec<Violation> {
+        self.violations.drain(..).collect()
+    }
+
+    /// Forget the conversation, with all the peers.
+    pub fn forget(&mut self, conversation_id: Id) {
+        self.protocols.remove(&conversation_id);
+        self.steps.remove(&conversation_id);
+    }
+}
+
+#[cfg(test)]
+mod test_conformance {
+
+    use std::time::Duration;
+
+    use super::*;
+    use message::{Content, Timestamp, new_id};
+    use protocol::auction::{AuctionContent, AuctionRules, AuctionState, Auctioneer};
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Task(u32);
+
+    impl Content for Task {}
+
+    impl AuctionContent for Task {
+        fn from_price(price: u64) -> Self { Task(price as u32) }
+
+        fn price(&self) -> Option<u64> { Some(u64::from(self.0)) }
+    }
+
+    const INITIATOR: (SystemId, AgentId) = (0, AgentId { index: 0, generation: 0 });
+    const PARTICIPANT: (SystemId, AgentId) = (0, AgentId { index: 1, generation: 0 });
+
+    fn message(performative: Performative, protocol: InteractionProtocol, conversation_id: Id) -> Message<Task> {
+        let mut message = Message::builder(performative, PARTICIPANT)
+            .protocol(protocol)
+            .conversation(conversation_id)
+            .content(Task(1))
+            .build();
+        message.set_sender(INITIATOR);
+        message
+    }
+
+    #[test]
+    fn it_should_follow_an_agreed_request() {
+        let mut checker = ConformanceChecker::new(ViolationPolicy::Reject);
+        let conversation_id = new_id();
+
+        for performative in vec![Performative::Request, Performative::Agree, Performative::Inform] {
+            assert!(checker.check_sent(&message(performative, InteractionProtocol::Request, conversation_id)));
+        }
+
+        assert_eq!(Step::Done, checker.step(conversation_id, PARTICIPANT));
+        assert!(checker.violations().is_empty());
+    }
+
+    #[test]
+    fn it_should_reject_an_accept_proposal_without_proposal() {
+        let mut checker = ConformanceChecker::new(ViolationPolicy::Reject);
+        let conversation_id = new_id();
+
+        assert!(checker.check_sent(&message(Performative::CallForProposal, InteractionProtocol::ContractNet, conversation_id)));
+        let accept = message(Performative::AcceptProposal, InteractionProtocol::ContractNet, conversation_id);
+        assert!(!checker.check_sent(&accept));
+
+        assert_eq!(Step::Called, checker.step(conversation_id, PARTICIPANT));
+        let violation = &checker.violations()[0];
+        assert_eq!(accept.id, violation.message_id);
+        assert_eq!(Step::Called, violation.step);
+        assert_eq!(PARTICIPANT, violation.peer);
+    }
+
+    #[test]
+    fn it_should_let_violations_through_when_logging() {
+        let mut checker = ConformanceChecker::new(ViolationPolicy::Log);
+        let conversation_id = new_id();
+
+        assert!(checker.check_received(&message(Performative::Inform, InteractionProtocol::Query, conversation_id)));
+
+        assert_eq!(1, checker.violations().len());
+        assert_eq!(INITIATOR, checker.violations()[0].peer);
+    }
+
+    #[test]
+    fn it_should_check_against_the_declared_protocol() {
+        let mut checker = ConformanceChecker::new(ViolationPolicy::Reject);
+        let conversation_id = new_id();
+        checker.declare(conversation_id, InteractionProtocol::Subscribe);
+
+        assert!(!checker.check_sent(&message(Performative::Request, InteractionProtocol::Request, conversation_id)));
+        assert!(checker.check_sent(&message(Performative::Subscribe, InteractionProtocol::Request, conversation_id)));
+        assert_eq!(Step::Subscribed, checker.step(conversation_id, PARTICIPANT));
+
+        checker.forget(conversation_id);
+        assert_eq!(Step::Start, checker.step(conversation_id, PARTICIPANT));
+    }
+
+    #[test]
+    fn it_should_follow_a_multi_round_english_auction() {
+        let mut checker = ConformanceChecker::new(ViolationPolicy::Reject);
+        let bidders = vec![PARTICIPANT, (0, AgentId::new(2, 0))];
+        let max_prices = [110, 120];
+        let rules = AuctionRules {
+            start_price: 100,
+            step: 10,
+            reserve_price: 0,
+            round_duration: Duration::from_secs(1),
+        };
+        let mut auctioneer: Auctioneer<Task> = Auctioneer::english(bidders.clone(), rules, Timestamp(0));
+        let mut now = Timestamp(0);
+        let mut rounds = 0;
+
+        while auctioneer.state() == AuctionState::Running {
+            for mut message in auctioneer.take_messages() {
+                message.set_sender(INITIATOR);
+                assert!(checker.check_sent(&message));
+
+                let index = bidders.iter()
+                    .position(|&(system_id, agent_id)| message.recipient == Recipient::Agent { system_id, agent_id })
+                    .expect("Should be sent to a bidder");
+
+                if message.performative == Performative::CallForProposal && u64::from(message.content.0) <= max_prices[index] {
+                    let mut bid = message.create_reply(Performative::Propose, message.content.clone());
+                    bid.set_sender(bidders[index]);
+                    assert!(checker.check_received(&bid));
+                    assert!(auctioneer.handle_message(&bid));
+                }
+            }
+
+            now = now + Duration::from_secs(1);
+            auctioneer.poll(now);
+            rounds += 1;
+        }
+
+        for mut message in auctioneer.take_messages() {
+            message.set_sender(INITIATOR);
+            assert!(checker.check_sent(&message));
+        }
+
+        assert_eq!(4, rounds);
+        assert_eq!(AuctionState::Sold, auctioneer.state());
+        assert!(checker.violations().is_empty());
+        assert!(checker.is_over(auctioneer.conversation_id()));
+    }
+
+    #[test]
+    fn it_should_keep_the_last_violations() {
+        let mut checker = ConformanceChecker::new(ViolationPolicy::Log);
+        let conversation_id = new_id();
+
+        for _ in 0..MAX_VIOLATIONS + 1 {
+            checker.check_sent(&message(Performative::AcceptProposal, InteractionProtocol::ContractNet, conversation_id));
+        }
+        assert_eq!(MAX_VIOLATIONS, checker.violations().len());
+
+        assert_eq!(MAX_VIOLATIONS, checker.take_violations().len());
+        assert!(checker.violations().is_empty());
+    }
+}
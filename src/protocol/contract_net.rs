@@ -0,0 +1,456 @@
+use std::{
+    collections::HashMap,
+    time::Duration,
+};
+
+use agent::AgentId;
+use agent_system::SystemId;
+use conversation::InteractionProtocol;
+use message::{Content, Id, Message, Performative, Timestamp, new_id};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InitiatorState {
+    /// Waiting for the proposals of the participants until the deadline.
+    CallingForProposals,
+    /// Waiting for the result of the accepted proposals.
+    AwaitingResults,
+    Done,
+}
+
+/// Initiator role: calls for proposals, lets the agent evaluate them, then collects
+/// the results of the accepted ones.
+pub struct ContractNetInitiator<C> {
+    conversation_id: Id,
+    state: InitiatorState,
+    deadline: Timestamp,
+    participants: Vec<(SystemId, AgentId)>,
+    answered: Vec<(SystemId, AgentId)>,
+    proposals: Vec<Message<C>>,
+    accepted: Vec<(SystemId, AgentId)>,
+    results: Vec<Message<C>>,
+    failures: Vec<Message<C>>,
+    outbox: Vec<Message<C>>,
+}
+
+impl<C: Content> ContractNetInitiator<C> {
+    /// Call the participants for proposals to perform the task before the deadline.
+    pub fn new(participants: Vec<(SystemId, AgentId)>, task: C, deadline: Timestamp) -> Self {
+        let conversation_id = new_id();
+        let outbox = participants.iter()
+            .map(|&participant| {
+                Message::call_for_proposal(participant)
+                    .protocol(InteractionProtocol::ContractNet)
+                    .conversation(conversation_id)
+                    .expect_reply()
+                    .reply_by(deadline)
+                    .content(task.clone())
+                    .build()
+            })
+            .collect();
+
+        ContractNetInitiator {
+            conversation_id,
+            state: InitiatorState::CallingForProposals,
+            deadline,
+            participants,
+            answered: Vec::new(),
+            proposals: Vec::new(),
+            accepted: Vec::new(),
+            results: Vec::new(),
+            failures: Vec::new(),
+            outbox,
+        }
+    }
+
+    pub fn conversation_id(&self) -> Id {
+        self.conversation_id
+    }
+
+    pub fn state(&self) -> InitiatorState {
+        self.state
+    }
+
+    /// Record the answer of a participant, returns false if the message isn't part of the protocol.
+    pub fn handle_message(&mut self, message: &Message<C>) -> bool {
+        if message.conversation_id != Some(self.conversation_id) || !self.participants.contains(&message.sender) {
+            return false;
+        }
+
+        match (self.state, &message.performative) {
+            (InitiatorState::CallingForProposals, &Performative::Propose) if !self.answered.contains(&message.sender) => {
+                self.answered.push(message.sender);
+                self.proposals.push(message.clone());
+            },
+            (InitiatorState::CallingForProposals, &Performative::Refuse) if !self.answered.contains(&message.sender) => {
+                self.answered.push(message.sender);
+            },
+            (InitiatorState::AwaitingResults, &Performative::Inform) if self.accepted.contains(&message.sender) => {
+                self.accepted.retain(|&participant| participant != message.sender);
+                self.results.push(message.clone());
+            },
+            (InitiatorState::AwaitingResults, &Performative::Failure) if self.accepted.contains(&message.sender) => {
+                self.accepted.retain(|&participant| participant != message.sender);
+                self.failures.push(message.clone());
+            },
+            _ => return false,
+        }
+
+        if self.state == InitiatorState::AwaitingResults && self.accepted.is_empty() {
+            self.state = InitiatorState::Done;
+        }
+
+        true
+    }
+
+    /// The proposals can be evaluated once every participant answered or the deadline is over.
+    pub fn is_ready_to_evaluate(&self, now: Timestamp) -> bool {
+        self.state == InitiatorState::CallingForProposals
+            && (self.answered.len() == self.participants.len() || self.deadline <= now)
+    }
+
+    /// Once ready, accept the proposals at the indexes returned by `evaluate` and reject the others.
+    /// The late proposals are ignored. Returns false if the proposals aren't ready to be evaluated.
+    pub fn evaluate<F>(&mut self, now: Timestamp, evaluate: F) -> bool
+        where F: FnOnce(&[Message<C>]) -> Vec<usize>
+    {
+        if !self.is_ready_to_evaluate(now) {
+            return false;
+        }
+
+        let accepted = evaluate(&self.proposals);
+
+        for (index, proposal) in self.proposals.iter().enumerate() {
+            let performative = if accepted.contains(&index) {
+                self.accepted.push(proposal.sender);
+                Performative::AcceptProposal
+            } else {
+                Performative::RejectProposal
+            };
+
+            let reply = Message::reply_to(proposal).performative(performative.clone());
+            let reply = if performative == Performative::AcceptProposal { reply.expect_reply() } else { reply };
+
+            self.outbox.push(reply.content(proposal.content.clone()).build());
+        }
+
+        self.state = if self.accepted.is_empty() {
+            InitiatorState::Done
+        } else {
+            InitiatorState::AwaitingResults
+        };
+
+        true
+    }
+
+    pub fn proposals(&self) -> &[Message<C>] {
+        &self.proposals
+    }
+
+    /// `Inform` sent by the participants which performed the task.
+    pub fn results(&self) -> &[Message<C>] {
+        &self.results
+    }
+
+    /// `Failure` sent by the participants which failed to perform the task.
+    pub fn failures(&self) -> &[Message<C>] {
+        &self.failures
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == InitiatorState::Done
+    }
+
+    /// Messages to send, to return from `act`.
+    pub fn take_messages(&mut self) -> Vec<Message<C>> {
+        self.outbox.drain(..).collect()
+    }
+}
+
+/// Step of the protocol reached by a message received by the responder.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ResponderEvent {
+    /// The agent should `propose` or `refuse`.
+    CallForProposal,
+    /// The agent should perform the task then send the `result` or a `failure`.
+    ProposalAccepted,
+    ProposalRejected,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ResponderState {
+    Called,
+    Proposed,
+    Accepted,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct ResponderConversation {
+    state: ResponderState,
+    /// `reply_by` of the call for proposals.
+    deadline: Option<Timestamp>,
+}
+
+/// Responder role: answers the calls for proposals and reports the result of the accepted ones.
+pub struct ContractNetResponder<C> {
+    conversations: HashMap<Id, ResponderConversation>,
+    outbox: Vec<Message<C>>,
+}
+
+impl<C: Content> ContractNetResponder<C> {
+    pub fn new() -> Self {
+        ContractNetResponder {
+            conversations: HashMap::new(),
+            outbox: Vec::new(),
+        }
+    }
+
+    /// Tell the agent what it has to do with the message, `None` if the message isn't part of the protocol.
+    pub fn handle_message(&mut self, message: &Message<C>) -> Option<ResponderEvent> {
+        let conversation_id = message.conversation_id?;
+        let state = self.state(conversation_id);
+
+        match (state, &message.performative) {
+            (None, &Performative::CallForProposal) => {
+                let conversation = ResponderConversation { state: ResponderState::Called, deadline: message.reply_by };
+                self.conversations.insert(conversation_id, conversation);
+                Some(ResponderEvent::CallForProposal)
+            },
+            (Some(ResponderState::Proposed), &Performative::AcceptProposal) => {
+                self.set_state(conversation_id, ResponderState::Accepted);
+                Some(ResponderEvent::ProposalAccepted)
+            },
+            (Some(ResponderState::Proposed), &Performative::RejectProposal) => {
+                self.conversations.remove(&conversation_id);
+                Some(ResponderEvent::ProposalRejected)
+            },
+            _ => None,
+        }
+    }
+
+    /// Answer the call for proposals with a proposal.
+    pub fn propose(&mut self, call: &Message<C>, proposal: C) {
+        self.reply(call, ResponderState::Called, Some(ResponderState::Proposed), Performative::Propose, proposal);
+    }
+
+    pub fn refuse(&mut self, call: &Message<C>, reason: C) {
+        self.reply(call, ResponderState::Called, None, Performative::Refuse, reason);
+    }
+
+    /// Report the result of the task of an accepted proposal.
+    pub fn result(&mut self, accepted: &Message<C>, result: C) {
+        self.reply(accepted, ResponderState::Accepted, None, Performative::Inform, result);
+    }
+
+    pub fn failure(&mut self, accepted: &Message<C>, reason: C) {
+        self.reply(accepted, ResponderState::Accepted, None, Performative::Failure, reason);
+    }
+
+    fn reply(&mut self, message: &Message<C>, expected: ResponderState, next: Option<ResponderState>,
+             performative: Performative, content: C)
+    {
+        let conversation_id = match message.conversation_id {
+            Some(conversation_id) if self.state(conversation_id) == Some(expected) => conversation_id,
+            _ => {
+                warn!("Can't answer {:?} to the message {} at this step of the contract net", performative, message.id);
+                return
+            },
+        };
+
+        let reply = Message::reply_to(message).performative(performative);
+        let reply = match next {
+            Some(state) => {
+                self.set_state(conversation_id, state);
+                reply.expect_reply()
+            },
+            None => {
+                self.conversations.remove(&conversation_id);
+                reply
+            },
+        };
+
+        self.outbox.push(reply.content(content).build());
+    }
+
+    fn state(&self, conversation_id: Id) -> Option<ResponderState> {
+        self.conversations.get(&conversation_id).map(|conversation| conversation.state)
+    }
+
+    fn set_state(&mut self, conversation_id: Id, state: ResponderState) {
+        if let Some(conversation) = self.conversations.get_mut(&conversation_id) {
+            conversation.state = state;
+        }
+    }
+
+    /// Forget the conversations without accepted proposal `grace` after the deadline of their call,
+    /// as the initiator evaluates the proposals at the deadline and ignores the late ones.
+    /// Calls without `reply_by` expire `grace` after the first `expire` seeing them.
+    /// Returns the ids of the conversations forgotten.
+    pub fn expire(&mut self, now: Timestamp, grace: Duration) -> Vec<Id> {
+        let mut expired = Vec::new();
+
+        self.conversations.retain(|&conversation_id, conversation| {
+            let deadline = *conversation.deadline.get_or_insert(now);
+            let over = conversation.state != ResponderState::Accepted && deadline + grace <= now;
+            if over {
+                expired.push(conversation_id);
+            }
+            !over
+        });
+        expired
+    }
+
+    /// Number of conversations the responder takes part in.
+    pub fn len(&self) -> usize {
+        self.conversations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conversations.is_empty()
+    }
+
+    /// Messages to send, to return from `act`.
+    pub fn take_messages(&mut self) -> Vec<Message<C>> {
+        self.outbox.drain(..).collect()
+    }
+}
+
+impl<C: Content> Default for ContractNetResponder<C> {
+    fn default() -> Self {
+        ContractNetResponder::new()
+    }
+}
+
+#[cfg(test)]
+mod test_contract_net {
+
+    use super::*;
+    use std::time::Duration;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    enum Task {
+        Job,
+        Bid(u8),
+        Busy,
+        Done,
+    }
+
+    impl Content for Task {}
+
+    const INITIATOR: (SystemId, AgentId) = (0, AgentId { index: 0, generation: 0 });
+
+    fn participant(index: usize) -> (SystemId, AgentId) {
+        (0, AgentId::new(index, 0))
+    }
+
+    fn sent_by(mut messages: Vec<Message<Task>>, sender: (SystemId, AgentId)) -> Vec<Message<Task>> {
+        for m in messages.iter_mut() {
+            m.set_sender(sender);
+        }
+        messages
+    }
+
+    #[test]
+    fn it_should_award_the_task_and_collect_the_result() {
+        let deadline = Timestamp(10_000);
+        let mut initiator = ContractNetInitiator::new(vec![participant(1), participant(2)], Task::Job, deadline);
+        let mut worker = ContractNetResponder::new();
+        let mut busy = ContractNetResponder::new();
+
+        let calls = sent_by(initiator.take_messages(), INITIATOR);
+        assert_eq!(2, calls.len());
+        assert!(calls.iter().all(|m| m.performative == Performative::CallForProposal && m.reply_by == Some(deadline)));
+
+        assert_eq!(Some(ResponderEvent::CallForProposal), worker.handle_message(&calls[0]));
+        worker.propose(&calls[0], Task::Bid(10));
+        assert_eq!(Some(ResponderEvent::CallForProposal), busy.handle_message(&calls[1]));
+        busy.refuse(&calls[1], Task::Busy);
+
+        for m in sent_by(worker.take_messages(), participant(1)).iter()
+            .chain(sent_by(busy.take_messages(), participant(2)).iter()) {
+            assert!(initiator.handle_message(m));
+        }
+
+        assert!(initiator.is_ready_to_evaluate(Timestamp(0)));
+        assert!(initiator.evaluate(Timestamp(0), |proposals| {
+            assert_eq!(Task::Bid(10), proposals[0].content);
+            vec![0]
+        }));
+        assert_eq!(InitiatorState::AwaitingResults, initiator.state());
+
+        let accepted = sent_by(initiator.take_messages(), INITIATOR);
+        assert_eq!(1, accepted.len());
+        assert_eq!(Performative::AcceptProposal, accepted[0].performative);
+        assert_eq!(Some(ResponderEvent::ProposalAccepted), worker.handle_message(&accepted[0]));
+        worker.result(&accepted[0], Task::Done);
+
+        for m in sent_by(worker.take_messages(), participant(1)) {
+            assert!(initiator.handle_message(&m));
+        }
+
+        assert!(initiator.is_done());
+        assert_eq!(Task::Done, initiator.results()[0].content);
+        assert!(initiator.failures().is_empty());
+    }
+
+    #[test]
+    fn it_should_evaluate_the_proposals_received_before_the_deadline() {
+        let deadline = Timestamp(10_000);
+        let mut initiator = ContractNetInitiator::new(vec![participant(1), participant(2)], Task::Job, deadline);
+        let mut late = ContractNetResponder::new();
+
+        let calls = sent_by(initiator.take_messages(), INITIATOR);
+        late.handle_message(&calls[1]);
+        late.propose(&calls[1], Task::Bid(1));
+
+        assert!(!initiator.is_ready_to_evaluate(Timestamp(5_000)));
+        assert!(initiator.evaluate(deadline + Duration::from_millis(1), |proposals| {
+            assert!(proposals.is_empty());
+            Vec::new()
+        }));
+        assert!(initiator.is_done());
+
+        for m in sent_by(late.take_messages(), participant(2)) {
+            assert!(!initiator.handle_message(&m));
+        }
+    }
+
+    #[test]
+    fn it_should_ignore_an_acceptance_without_proposal() {
+        let mut initiator = ContractNetInitiator::new(vec![participant(1)], Task::Job, Timestamp(0));
+        let mut responder = ContractNetResponder::new();
+
+        let call = sent_by(initiator.take_messages(), INITIATOR).remove(0);
+        let accept = call.create_reply(Performative::AcceptProposal, Task::Job);
+
+        assert_eq!(None, responder.handle_message(&accept));
+        assert_eq!(Some(ResponderEvent::CallForProposal), responder.handle_message(&call));
+        assert_eq!(None, responder.handle_message(&accept));
+    }
+
+    #[test]
+    fn it_should_forget_a_proposal_the_initiator_never_answered() {
+        let deadline = Timestamp(10_000);
+        let mut initiator = ContractNetInitiator::new(vec![participant(1), participant(2)], Task::Job, deadline);
+        let mut ignored = ContractNetResponder::new();
+        let mut worker = ContractNetResponder::new();
+
+        let calls = sent_by(initiator.take_messages(), INITIATOR);
+        ignored.handle_message(&calls[0]);
+        ignored.propose(&calls[0], Task::Bid(1));
+        worker.handle_message(&calls[1]);
+        worker.propose(&calls[1], Task::Bid(2));
+        let proposal = sent_by(worker.take_messages(), participant(2)).remove(0);
+        let accept = proposal.create_reply(Performative::AcceptProposal, Task::Job);
+        worker.handle_message(&accept);
+
+        let grace = Duration::from_secs(1);
+        assert!(ignored.expire(deadline, grace).is_empty());
+        assert_eq!(vec![initiator.conversation_id()], ignored.expire(deadline + grace, grace));
+        assert!(ignored.is_empty());
+        assert!(worker.expire(deadline + grace, grace).is_empty());
+        assert_eq!(1, worker.len());
+
+        let late = calls[0].create_reply(Performative::AcceptProposal, Task::Job);
+        assert_eq!(None, ignored.handle_message(&late));
+    }
+}
@@ -0,0 +1,16 @@
+/// Initiator and responder roles of the FIPA Contract Net protocol.
+pub mod contract_net;
+/// Initiator and responder roles of the FIPA Request and Query protocols.
+pub mod request;
+/// Auctioneer and bidder roles of the FIPA English and Dutch auction protocols.
+pub mod auction;
+/// Checker of the performatives exchanged in the conversations against their protocol.
+pub mod conformance;
+
+use message::{Content, Message, Performative};
+
+/// Answer to a message which doesn't follow the protocol of its conversation.
+pub fn not_understood<C: Content>(message: &Message<C>) -> Message<C> {
+    warn!("The message {} doesn't follow its protocol: {:?}", message.id, message.performative);
+    message.create_reply(Performative::NotUnderstood, message.content.clone())
+}
@@ -0,0 +1,506 @@
+use std::collections::{HashMap, HashSet};
+
+use agent::AgentId;
+use agent_system::SystemId;
+use conversation::InteractionProtocol;
+use message::{Content, Id, Message, Performative, new_id};
+use protocol::not_understood;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RequestState {
+    /// Waiting for the participant to agree, refuse or answer.
+    Requested,
+    /// The participant agreed and will answer.
+    Agreed,
+    Refused,
+    Done,
+    Failed,
+    /// The participant didn't understand the request.
+    NotUnderstood,
+    /// The participant cancelled the request, on demand of the initiator.
+    Cancelled,
+}
+
+/// Initiator role of the FIPA Request and Query protocols: asks one participant
+/// and follows its answers. Any other answer is replied with `NotUnderstood`, except a
+/// `NotUnderstood` which is never answered.
+pub struct RequestInitiator<C> {
+    conversation_id: Id,
+    protocol: InteractionProtocol,
+    participant: (SystemId, AgentId),
+    state: RequestState,
+    /// `reply_with` of the cancel waiting for an answer.
+    cancel: Option<Id>,
+    response: Option<Message<C>>,
+    outbox: Vec<Message<C>>,
+}
+
+impl<C: Content> RequestInitiator<C> {
+    /// Request the participant to perform the action.
+    pub fn request(participant: (SystemId, AgentId), action: C) -> Self {
+        RequestInitiator::new(Performative::Request, InteractionProtocol::Request, participant, action)
+    }
+
+    /// Ask the participant whether the proposition is true.
+    pub fn query_if(participant: (SystemId, AgentId), proposition: C) -> Self {
+        RequestInitiator::new(Performative::QueryIf, InteractionProtocol::Query, participant, proposition)
+    }
+
+    /// Ask the participant for the object referred to by the expression.
+    pub fn query_ref(participant: (SystemId, AgentId), expression: C) -> Self {
+        RequestInitiator::new(Performative::QueryRef, InteractionProtocol::Query, participant, expression)
+    }
+
+    fn new(performative: Performative, protocol: InteractionProtocol, participant: (SystemId, AgentId), content: C) -> Self {
+        let conversation_id = new_id();
+        let request = Message::builder(performative, participant)
+            .protocol(protocol)
+            .conversation(conversation_id)
+            .expect_reply()
+            .content(content)
+            .build();
+
+        RequestInitiator {
+            conversation_id,
+            protocol,
+            participant,
+            state: RequestState::Requested,
+            cancel: None,
+            response: None,
+            outbox: vec![request],
+        }
+    }
+
+    pub fn conversation_id(&self) -> Id {
+        self.conversation_id
+    }
+
+    pub fn state(&self) -> RequestState {
+        self.state
+    }
+
+    /// Last answer of the participant.
+    pub fn response(&self) -> Option<&Message<C>> {
+        self.response.as_ref()
+    }
+
+    pub fn is_done(&self) -> bool {
+        !matches!(self.state, RequestState::Requested | RequestState::Agreed)
+    }
+
+    /// Ask the participant to cancel the request, it answers `Inform` once cancelled, or `Failure`
+    /// if the request goes on.
+    pub fn cancel(&mut self, action: C) {
+        if self.is_done() || self.cancel.is_some() {
+            warn!("Can't cancel the request {} at this step of the protocol", self.conversation_id);
+            return;
+        }
+
+        let cancel = Message::builder(Performative::Cancel, self.participant)
+            .protocol(self.protocol)
+            .conversation(self.conversation_id)
+            .expect_reply()
+            .content(action)
+            .build();
+        self.cancel = cancel.reply_with;
+        self.outbox.push(cancel);
+    }
+
+    /// Follow the answer of the participant, returns false if the message isn't an expected answer.
+    pub fn handle_message(&mut self, message: &Message<C>) -> bool {
+        if message.conversation_id != Some(self.conversation_id) || message.sender != self.participant {
+            return false;
+        }
+
+        if self.cancel.is_some() && message.in_reply_to == self.cancel {
+            match message.performative {
+                Performative::Inform => {
+                    self.cancel = None;
+                    self.state = RequestState::Cancelled;
+                    self.response = Some(message.clone());
+                    return true;
+                },
+                Performative::Failure => {
+                    self.cancel = None;
+                    return true;
+                },
+                _ => {},
+            }
+        }
+
+        let state = match (self.state, &message.performative) {
+            (RequestState::Requested, &Performative::Agree) => RequestState::Agreed,
+            (RequestState::Requested, &Performative::Refuse) => RequestState::Refused,
+            (RequestState::Requested, &Performative::NotUnderstood) => RequestState::NotUnderstood,
+            (RequestState::Requested, &Performative::Inform) | (RequestState::Agreed, &Performative::Inform) => {
+                RequestState::Done
+            },
+            (RequestState::Requested, &Performative::Failure) | (RequestState::Agreed, &Performative::Failure) => {
+                RequestState::Failed
+            },
+            (_, &Performative::NotUnderstood) => return false,
+            _ => {
+                self.outbox.push(not_understood(message));
+                return false;
+            },
+        };
+
+        self.state = state;
+        self.response = Some(message.clone());
+        true
+    }
+
+    /// Messages to send, to return from `act`.
+    pub fn take_messages(&mut self) -> Vec<Message<C>> {
+        self.outbox.drain(..).collect()
+    }
+}
+
+/// Request received by the responder.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RequestEvent {
+    /// The agent should `agree` or `refuse`, then `inform` or report a `failure`.
+    Request,
+    /// The agent should `inform` or `refuse`.
+    QueryIf,
+    QueryRef,
+    /// The initiator cancels its request, the agent should answer with `cancelled` or `cancel_failed`.
+    Cancel,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ResponderState {
+    Received,
+    Agreed,
+}
+
+/// Responder role of the FIPA Request and Query protocols.
+/// The messages breaking these protocols are replied with `NotUnderstood`, the messages of
+/// unknown conversations other than new requests are left to the agent.
+pub struct RequestResponder<C> {
+    conversations: HashMap<Id, ResponderState>,
+    /// Conversations whose initiator asked to cancel the request.
+    cancels: HashSet<Id>,
+    outbox: Vec<Message<C>>,
+}
+
+impl<C: Content> RequestResponder<C> {
+    pub fn new() -> Self {
+        RequestResponder {
+            conversations: HashMap::new(),
+            cancels: HashSet::new(),
+            outbox: Vec::new(),
+        }
+    }
+
+    /// Tell the agent what it has been asked, `None` if the message isn't a new request.
+    pub fn handle_message(&mut self, message: &Message<C>) -> Option<RequestEvent> {
+        let in_protocol = matches!(message.protocol, Some(InteractionProtocol::Request) | Some(InteractionProtocol::Query));
+        let new_request = in_protocol
+            && matches!(message.performative, Performative::Request | Performative::QueryIf | Performative::QueryRef);
+        let known = message.conversation_id.is_some_and(|id| self.conversations.contains_key(&id));
+
+        // The other messages may answer the requests of the agent.
+        if !(known || new_request) {
+            return None;
+        }
+
+        if message.performative == Performative::NotUnderstood {
+            return None;
+        }
+
+        if known && message.performative == Performative::Cancel {
+            if let Some(id) = message.conversation_id {
+                if self.cancels.insert(id) {
+                    return Some(RequestEvent::Cancel);
+                }
+            }
+        }
+
+        let event = match (known, &message.performative, message.protocol) {
+            (false, &Performative::Request, Some(InteractionProtocol::Request)) => RequestEvent::Request,
+            (false, &Performative::QueryIf, Some(InteractionProtocol::Query)) => RequestEvent::QueryIf,
+            (false, &Performative::QueryRef, Some(InteractionProtocol::Query)) => RequestEvent::QueryRef,
+            _ => {
+                self.outbox.push(not_understood(message));
+                return None;
+            },
+        };
+
+        match message.conversation_id {
+            Some(id) => {
+                self.conversations.insert(id, ResponderState::Received);
+                Some(event)
+            },
+            None => {
+                self.outbox.push(not_understood(message));
+                None
+            },
+        }
+    }
+
+    /// Agree to perform the requested action, the result is sent later with `inform` or `failure`.
+    pub fn agree(&mut self, request: &Message<C>, content: C) {
+        if self.answer(request, Performative::Agree, content) {
+            if let Some(id) = request.conversation_id {
+                self.conversations.insert(id, ResponderState::Agreed);
+            }
+        }
+    }
+
+    pub fn refuse(&mut self, request: &Message<C>, reason: C) {
+        self.answer(request, Performative::Refuse, reason);
+    }
+
+    pub fn inform(&mut self, request: &Message<C>, result: C) {
+        self.answer(request, Performative::Inform, result);
+    }
+
+    pub fn failure(&mut self, request: &Message<C>, reason: C) {
+        self.answer(request, Performative::Failure, reason);
+    }
+
+    /// Confirm the request has been cancelled, with an `Inform`.
+    pub fn cancelled(&mut self, cancel: &Message<C>, content: C) {
+        if self.answer_cancel(cancel, Performative::Inform, content) {
+            if let Some(id) = cancel.conversation_id {
+                self.conversations.remove(&id);
+            }
+        }
+    }
+
+    /// Tell the initiator the request can't be cancelled, with a `Failure`. The request goes on.
+    pub fn cancel_failed(&mut self, cancel: &Message<C>, reason: C) {
+        self.answer_cancel(cancel, Performative::Failure, reason);
+    }
+
+    fn answer_cancel(&mut self, cancel: &Message<C>, performative: Performative, content: C) -> bool {
+        if !cancel.conversation_id.is_some_and(|id| self.cancels.remove(&id)) {
+            warn!("Can't answer {:?} to the cancel {} which hasn't been received", performative, cancel.id);
+            return false;
+        }
+
+        self.outbox.push(cancel.create_reply(performative, content));
+        true
+    }
+
+    fn answer(&mut self, request: &Message<C>, performative: Performative, content: C) -> bool {
+        let state = request.conversation_id.and_then(|id| self.conversations.remove(&id));
+
+        let allowed = match state {
+            Some(ResponderState::Received) => true,
+            Some(ResponderState::Agreed) => matches!(performative, Performative::Inform | Performative::Failure),
+            None => false,
+        };
+
+        if !allowed {
+            warn!("Can't answer {:?} to the request {} at this step of the protocol", performative, request.id);
+            if let (Some(state), Some(id)) = (state, request.conversation_id) {
+                self.conversations.insert(id, state);
+            }
+            return false;
+        }
+
+        self.outbox.push(request.create_reply(performative, content));
+        true
+    }
+
+    /// Messages to send, to return from `act`.
+    pub fn take_messages(&mut self) -> Vec<Message<C>> {
+        self.outbox.drain(..).collect()
+    }
+}
+
+impl<C: Content> Default for RequestResponder<C> {
+    fn default() -> Self {
+        RequestResponder::new()
+    }
+}
+
+#[cfg(test)]
+mod test_request {
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    enum Action {
+        OpenDoor,
+        DoorOpened,
+        IsDoorOpen,
+        Yes,
+    }
+
+    impl Content for Action {}
+
+    const INITIATOR: (SystemId, AgentId) = (0, AgentId { index: 0, generation: 0 });
+    const PARTICIPANT: (SystemId, AgentId) = (0, AgentId { index: 1, generation: 0 });
+
+    fn sent_by(mut messages: Vec<Message<Action>>, sender: (SystemId, AgentId)) -> Vec<Message<Action>> {
+        for m in messages.iter_mut() {
+            m.set_sender(sender);
+        }
+        messages
+    }
+
+    #[test]
+    fn it_should_follow_an_agreed_request_until_its_result() {
+        let mut initiator = RequestInitiator::request(PARTICIPANT, Action::OpenDoor);
+        let mut responder = RequestResponder::new();
+
+        let request = sent_by(initiator.take_messages(), INITIATOR).remove(0);
+        assert_eq!(Some(RequestEvent::Request), responder.handle_message(&request));
+
+        responder.agree(&request, Action::OpenDoor);
+        responder.inform(&request, Action::DoorOpened);
+        let answers = sent_by(responder.take_messages(), PARTICIPANT);
+
+        assert!(initiator.handle_message(&answers[0]));
+        assert_eq!(RequestState::Agreed, initiator.state());
+        assert!(initiator.handle_message(&answers[1]));
+        assert_eq!(RequestState::Done, initiator.state());
+        assert_eq!(Action::DoorOpened, initiator.response().expect("Should keep the result").content);
+        assert!(initiator.take_messages().is_empty());
+    }
+
+    #[test]
+    fn it_should_answer_a_query() {
+        let mut initiator = RequestInitiator::query_if(PARTICIPANT, Action::IsDoorOpen);
+        let mut responder = RequestResponder::new();
+
+        let query = sent_by(initiator.take_messages(), INITIATOR).remove(0);
+        assert_eq!(Performative::QueryIf, query.performative);
+        assert_eq!(Some(RequestEvent::QueryIf), responder.handle_message(&query));
+
+        responder.inform(&query, Action::Yes);
+        let answer = sent_by(responder.take_messages(), PARTICIPANT).remove(0);
+
+        assert!(initiator.handle_message(&answer));
+        assert!(initiator.is_done());
+    }
+
+    #[test]
+    fn it_should_not_understand_an_answer_breaking_the_protocol() {
+        let mut initiator = RequestInitiator::request(PARTICIPANT, Action::OpenDoor);
+
+        let request = sent_by(initiator.take_messages(), INITIATOR).remove(0);
+        let mut accept = request.create_reply(Performative::AcceptProposal, Action::OpenDoor);
+        accept.set_sender(PARTICIPANT);
+
+        assert!(!initiator.handle_message(&accept));
+        assert_eq!(RequestState::Requested, initiator.state());
+
+        let replies = initiator.take_messages();
+        assert_eq!(1, replies.len());
+        assert_eq!(Performative::NotUnderstood, replies[0].performative);
+        assert_eq!(accept.reply_with, replies[0].in_reply_to);
+    }
+
+    #[test]
+    fn it_should_not_answer_a_not_understood() {
+        let mut initiator = RequestInitiator::request(PARTICIPANT, Action::OpenDoor);
+        let mut responder = RequestResponder::new();
+
+        let request = sent_by(initiator.take_messages(), INITIATOR).remove(0);
+        responder.handle_message(&request);
+        responder.agree(&request, Action::OpenDoor);
+        assert!(initiator.handle_message(&sent_by(responder.take_messages(), PARTICIPANT)[0]));
+
+        // The participant doesn't understand the request sent twice.
+        assert_eq!(None, responder.handle_message(&request));
+        let not_understood = sent_by(responder.take_messages(), PARTICIPANT).remove(0);
+        assert!(!initiator.handle_message(&not_understood));
+        assert!(initiator.take_messages().is_empty());
+
+        // The initiator doesn't understand an answer breaking the protocol.
+        let mut accept = request.create_reply(Performative::AcceptProposal, Action::OpenDoor);
+        accept.set_sender(PARTICIPANT);
+        assert!(!initiator.handle_message(&accept));
+        let not_understood = sent_by(initiator.take_messages(), INITIATOR).remove(0);
+        assert_eq!(None, responder.handle_message(&not_understood));
+        assert!(responder.take_messages().is_empty());
+        assert_eq!(RequestState::Agreed, initiator.state());
+    }
+
+    #[test]
+    fn it_should_leave_the_answers_to_the_requests_of_the_agent() {
+        let mut responder = RequestResponder::new();
+        let request = sent_by(RequestInitiator::request(PARTICIPANT, Action::OpenDoor).take_messages(), INITIATOR).remove(0);
+        let mut agree = request.create_reply(Performative::Agree, Action::OpenDoor);
+        agree.set_sender(PARTICIPANT);
+
+        assert_eq!(None, responder.handle_message(&agree));
+        assert!(responder.take_messages().is_empty());
+    }
+
+    #[test]
+    fn it_should_not_understand_a_request_sent_twice() {
+        let mut initiator = RequestInitiator::request(PARTICIPANT, Action::OpenDoor);
+        let mut responder = RequestResponder::new();
+
+        let request = sent_by(initiator.take_messages(), INITIATOR).remove(0);
+
+        assert_eq!(Some(RequestEvent::Request), responder.handle_message(&request));
+        assert_eq!(None, responder.handle_message(&request));
+        assert_eq!(Performative::NotUnderstood, responder.take_messages()[0].performative);
+    }
+
+    #[test]
+    fn it_should_not_inform_before_a_request() {
+        let mut responder: RequestResponder<Action> = RequestResponder::new();
+        let request = sent_by(RequestInitiator::request(PARTICIPANT, Action::OpenDoor).take_messages(), INITIATOR).remove(0);
+
+        responder.inform(&request, Action::DoorOpened);
+
+        assert!(responder.take_messages().is_empty());
+    }
+
+    #[test]
+    fn it_should_cancel_an_agreed_request() {
+        let mut initiator = RequestInitiator::request(PARTICIPANT, Action::OpenDoor);
+        let mut responder = RequestResponder::new();
+
+        let request = sent_by(initiator.take_messages(), INITIATOR).remove(0);
+        responder.handle_message(&request);
+        responder.agree(&request, Action::OpenDoor);
+        assert!(initiator.handle_message(&sent_by(responder.take_messages(), PARTICIPANT)[0]));
+
+        initiator.cancel(Action::OpenDoor);
+        let cancel = sent_by(initiator.take_messages(), INITIATOR).remove(0);
+        assert_eq!(Performative::Cancel, cancel.performative);
+        assert_eq!(Some(RequestEvent::Cancel), responder.handle_message(&cancel));
+
+        responder.cancelled(&cancel, Action::OpenDoor);
+        let answer = sent_by(responder.take_messages(), PARTICIPANT).remove(0);
+        assert_eq!(Performative::Inform, answer.performative);
+        assert_eq!(cancel.reply_with, answer.in_reply_to);
+
+        assert!(initiator.handle_message(&answer));
+        assert_eq!(RequestState::Cancelled, initiator.state());
+        assert!(initiator.is_done());
+
+        responder.inform(&request, Action::DoorOpened);
+        assert!(responder.take_messages().is_empty());
+    }
+
+    #[test]
+    fn it_should_go_on_with_a_request_which_cant_be_cancelled() {
+        let mut initiator = RequestInitiator::request(PARTICIPANT, Action::OpenDoor);
+        let mut responder = RequestResponder::new();
+
+        let request = sent_by(initiator.take_messages(), INITIATOR).remove(0);
+        responder.handle_message(&request);
+        initiator.cancel(Action::OpenDoor);
+        let cancel = sent_by(initiator.take_messages(), INITIATOR).remove(0);
+        assert_eq!(Some(RequestEvent::Cancel), responder.handle_message(&cancel));
+
+        responder.cancel_failed(&cancel, Action::DoorOpened);
+        responder.inform(&request, Action::DoorOpened);
+        let answers = sent_by(responder.take_messages(), PARTICIPANT);
+        assert_eq!(Performative::Failure, answers[0].performative);
+
+        assert!(initiator.handle_message(&answers[0]));
+        assert_eq!(RequestState::Requested, initiator.state());
+        assert!(initiator.handle_message(&answers[1]));
+        assert_eq!(RequestState::Done, initiator.state());
+    }
+}
@@ -0,0 +1,91 @@
+use std::{any::Any, time::Duration};
+
+use uuid::Uuid;
+
+use agent::{Agent, AgentId};
+use agent_factory::AgentFactory;
+use agent_system::SystemId;
+
+/// What the system does with an agent which panicked.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SupervisionStrategy {
+    /// Replace the agent by a new one created by the factory which spawned it, with the same id.
+    Restart,
+    /// Remove the agent from the system.
+    Stop,
+    /// Propagate the panic to the thread running the system.
+    Escalate,
+}
+
+/// Report of an agent which panicked.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AgentFailure {
+    pub system_id: SystemId,
+    pub agent_id: AgentId,
+    /// Message the agent was handling when it panicked, `None` if it was acting.
+    pub message_id: Option<Uuid>,
+    pub reason: String,
+}
+
+pub fn panic_reason(panic: &(dyn Any + Send)) -> String {
+    if let Some(reason) = panic.downcast_ref::<&str>() {
+        reason.to_string()
+    } else if let Some(reason) = panic.downcast_ref::<String>() {
+        reason.clone()
+    } else {
+        "unknown reason".to_string()
+    }
+}
+
+pub type SupervisorId = usize;
+
+/// Which children a supervisor restarts when one of them fails.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RestartStrategy {
+    /// Only the child which failed.
+    OneForOne,
+    /// Every child of the supervisor.
+    OneForAll,
+    /// The child which failed and the children started after it.
+    RestForOne,
+}
+
+/// A supervisor restarting more than `max_restarts` children `within` the duration gives up:
+/// it stops all its children and fails in turn.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RestartIntensity {
+    pub max_restarts: usize,
+    pub within: Duration,
+}
+
+pub enum ChildSpec<A> {
+    Worker(Box<dyn AgentFactory<A> + Send>),
+    Supervisor(SupervisorSpec<A>),
+}
+
+/// Description of a supervision tree. The children are started in order.
+pub struct SupervisorSpec<A> {
+    pub strategy: RestartStrategy,
+    pub intensity: RestartIntensity,
+    pub children: Vec<ChildSpec<A>>,
+}
+
+impl <A: Agent>SupervisorSpec<A> {
+    pub fn new(strategy: RestartStrategy, intensity: RestartIntensity) -> Self {
+        SupervisorSpec {
+            strategy,
+            intensity,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn worker(mut self, factory: Box<dyn AgentFactory<A> + Send>) -> Self {
+        self.children.push(ChildSpec::Worker(factory));
+        self
+    }
+
+    pub fn supervisor(mut self, spec: SupervisorSpec<A>) -> Self {
+        self.children.push(ChildSpec::Supervisor(spec));
+        self
+    }
+}
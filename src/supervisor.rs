@@ -0,0 +1,114 @@
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    time::Instant,
+};
+
+use agent::AgentId;
+use agent_factory::AgentFactory;
+use supervision::{RestartIntensity, RestartStrategy, SupervisorId};
+
+pub enum Child<A> {
+    Worker { factory: Box<dyn AgentFactory<A> + Send>, agent: Option<AgentId> },
+    Supervisor(SupervisorId),
+}
+
+pub struct Supervisor<A> {
+    pub parent: Option<SupervisorId>,
+    pub children: Vec<Child<A>>,
+    strategy: RestartStrategy,
+    intensity: RestartIntensity,
+    restarts: VecDeque<Instant>,
+}
+
+impl <A>Supervisor<A> {
+    pub fn new(parent: Option<SupervisorId>, strategy: RestartStrategy, intensity: RestartIntensity) -> Self {
+        Supervisor {
+            parent,
+            children: Vec::new(),
+            strategy,
+            intensity,
+            restarts: VecDeque::new(),
+        }
+    }
+
+    /// Record a restart, returns false if the supervisor exceeds its restart intensity.
+    pub fn record_restart(&mut self, now: Instant) -> bool {
+        while let Some(&oldest) = self.restarts.front() {
+            if now.duration_since(oldest) > self.intensity.within {
+                self.restarts.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        self.restarts.push_back(now);
+        self.restarts.len() <= self.intensity.max_restarts
+    }
+
+    pub fn reset_restarts(&mut self) {
+        self.restarts.clear();
+    }
+
+    /// Indexes of the children to restart when the child at `failed` fails.
+    pub fn children_to_restart(&self, failed: usize) -> Range<usize> {
+        match self.strategy {
+            RestartStrategy::OneForOne => failed..failed + 1,
+            RestartStrategy::OneForAll => 0..self.children.len(),
+            RestartStrategy::RestForOne => failed..self.children.len(),
+        }
+    }
+
+    pub fn index_of_supervisor(&self, supervisor_id: SupervisorId) -> Option<usize> {
+        self.children.iter().position(|child| match *child {
+            Child::Supervisor(id) => id == supervisor_id,
+            _ => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_supervisor {
+
+    use super::*;
+    use std::time::Duration;
+
+    fn supervisor(strategy: RestartStrategy, max_restarts: usize) -> Supervisor<()> {
+        let mut supervisor = Supervisor::new(None, strategy, RestartIntensity {
+            max_restarts,
+            within: Duration::from_secs(60),
+        });
+
+        for _ in 0..4 {
+            supervisor.children.push(Child::Supervisor(0));
+        }
+
+        supervisor
+    }
+
+    #[test]
+    fn it_should_select_the_children_to_restart_from_the_strategy() {
+        assert_eq!(2..3, supervisor(RestartStrategy::OneForOne, 1).children_to_restart(2));
+        assert_eq!(0..4, supervisor(RestartStrategy::OneForAll, 1).children_to_restart(2));
+        assert_eq!(2..4, supervisor(RestartStrategy::RestForOne, 1).children_to_restart(2));
+    }
+
+    #[test]
+    fn it_should_exceed_the_restart_intensity() {
+        let mut supervisor = supervisor(RestartStrategy::OneForOne, 2);
+        let now = Instant::now();
+
+        assert!(supervisor.record_restart(now));
+        assert!(supervisor.record_restart(now));
+        assert!(!supervisor.record_restart(now));
+    }
+
+    #[test]
+    fn it_should_forget_the_restarts_out_of_the_intensity_window() {
+        let mut supervisor = supervisor(RestartStrategy::OneForOne, 1);
+        let now = Instant::now();
+
+        assert!(supervisor.record_restart(now));
+        assert!(supervisor.record_restart(now + Duration::from_secs(61)));
+    }
+}
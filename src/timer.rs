@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use message::{Id, Message, Timestamp, new_id};
+
+/// Handle of a scheduled message, to cancel it before its delivery.
+pub type TimerId = Id;
+
+/// When a scheduled message is delivered.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Delay {
+    /// After this number of runs of the system, at least one.
+    Ticks(u64),
+    /// Once the duration has elapsed.
+    After(Duration),
+    /// Once the wall-clock time is reached.
+    At(Timestamp),
+}
+
+pub enum TimerRequest<C> {
+    Schedule { id: TimerId, delay: Delay, message: Message<C> },
+    Cancel(TimerId),
+}
+
+/// Timers of an agent: the system collects the messages scheduled and the timers cancelled after `act`.
+pub struct Timers<C> {
+    requests: Vec<TimerRequest<C>>,
+}
+
+impl<C> Timers<C> {
+    pub fn new() -> Self {
+        Timers { requests: Vec::new() }
+    }
+
+    /// Send the message once the delay has elapsed.
+    pub fn schedule(&mut self, delay: Delay, message: Message<C>) -> TimerId {
+        let id = new_id();
+        self.requests.push(TimerRequest::Schedule { id, delay, message });
+        id
+    }
+
+    /// Cancel a message scheduled by the agent, if not delivered yet.
+    pub fn cancel(&mut self, id: TimerId) {
+        self.requests.push(TimerRequest::Cancel(id));
+    }
+
+    pub fn take_requests(&mut self) -> Vec<TimerRequest<C>> {
+        self.requests.drain(..).collect()
+    }
+}
+
+impl<C> Default for Timers<C> {
+    fn default() -> Self {
+        Timers::new()
+    }
+}
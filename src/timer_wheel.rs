@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+
+use message::{Message, Timestamp};
+use timer::{Delay, TimerId};
+
+const NB_SLOTS: usize = 64;
+
+struct Timer<C> {
+    id: TimerId,
+    /// Number of turns of the wheel left before the timer fires when its slot comes.
+    rounds: u64,
+    message: Message<C>,
+}
+
+#[derive(Clone, Copy)]
+enum Location {
+    Slot(usize),
+    Deadline(Timestamp),
+}
+
+/// Timer saved by a checkpoint, due after a number of ticks or at a wall-clock time.
+#[derive(Serialize, Deserialize)]
+pub struct PendingTimer<C> {
+    pub id: TimerId,
+    pub due: Delay,
+    pub message: Message<C>,
+}
+
+/// Hashed timer wheel of the messages scheduled in ticks, with the ones scheduled at a
+/// wall-clock time kept by deadline.
+pub struct TimerWheel<C> {
+    slots: Vec<Vec<Timer<C>>>,
+    current: usize,
+    deadlines: BTreeMap<Timestamp, Vec<(TimerId, Message<C>)>>,
+    locations: HashMap<TimerId, Location>,
+}
+
+impl <C>TimerWheel<C> {
+    pub fn new() -> Self {
+        TimerWheel {
+            slots: (0..NB_SLOTS).map(|_| Vec::new()).collect(),
+            current: 0,
+            deadlines: BTreeMap::new(),
+            locations: HashMap::new(),
+        }
+    }
+
+    /// Schedule the message, replacing the timer with the same id if it's still pending.
+    pub fn schedule(&mut self, id: TimerId, delay: Delay, message: Message<C>, now: Timestamp) {
+        self.cancel(id);
+
+        match delay {
+            Delay::Ticks(ticks) => {
+                let ticks = ticks.max(1);
+                let slot = (self.current + (ticks % NB_SLOTS as u64) as usize) % NB_SLOTS;
+                let rounds = (ticks - 1) / NB_SLOTS as u64;
+                self.slots[slot].push(Timer { id, rounds, message });
+                self.locations.insert(id, Location::Slot(slot));
+            },
+            Delay::After(duration) => self.schedule(id, Delay::At(now + duration), message, now),
+            Delay::At(deadline) => {
+                self.deadlines.entry(deadline).or_default().push((id, message));
+                self.locations.insert(id, Location::Deadline(deadline));
+            },
+        }
+    }
+
+    /// Remove the timer, returns its message if it hasn't fired yet.
+    pub fn cancel(&mut self, id: TimerId) -> Option<Message<C>> {
+        match self.locations.remove(&id)? {
+            Location::Slot(slot) => {
+                let position = self.slots[slot].iter().position(|timer| timer.id == id)?;
+                Some(self.slots[slot].remove(position).message)
+            },
+            Location::Deadline(deadline) => {
+                let timers = self.deadlines.get_mut(&deadline)?;
+                let position = timers.iter().position(|&(timer_id, _)| timer_id == id)?;
+                let (_, message) = timers.remove(position);
+                if timers.is_empty() {
+                    self.deadlines.remove(&deadline);
+                }
+                Some(message)
+            },
+        }
+    }
+
+    /// Move the wheel by one tick, and return the messages of the timers firing at this tick
+    /// or whose deadline is passed.
+    pub fn advance(&mut self, now: Timestamp) -> Vec<Message<C>> {
+        self.current = (self.current + 1) % NB_SLOTS;
+
+        let mut messages = Vec::new();
+        for mut timer in mem::take(&mut self.slots[self.current]) {
+            if timer.rounds == 0 {
+                self.locations.remove(&timer.id);
+                messages.push(timer.message);
+            } else {
+                timer.rounds -= 1;
+                self.slots[self.current].push(timer);
+            }
+        }
+
+        let later = self.deadlines.split_off(&Timestamp(now.0 + 1));
+        for (_, timers) in mem::replace(&mut self.deadlines, later) {
+            for (id, message) in timers {
+                self.locations.remove(&id);
+                messages.push(message);
+            }
+        }
+
+        messages
+    }
+
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+}
+
+impl <C: Clone>TimerWheel<C> {
+    /// Timers not fired yet, with the number of ticks left for the ones scheduled in ticks.
+    pub fn pending(&self) -> Vec<PendingTimer<C>> {
+        let mut pending = Vec::new();
+
+        for offset in 1..=NB_SLOTS {
+            let slot = (self.current + offset) % NB_SLOTS;
+            for timer in &self.slots[slot] {
+                let due = Delay::Ticks(timer.rounds * NB_SLOTS as u64 + offset as u64);
+                pending.push(PendingTimer { id: timer.id, due, message: timer.message.clone() });
+            }
+        }
+
+        for (&deadline, timers) in &self.deadlines {
+            for &(id, ref message) in timers {
+                pending.push(PendingTimer { id, due: Delay::At(deadline), message: message.clone() });
+            }
+        }
+
+        pending
+    }
+}
+
+impl <C>Default for TimerWheel<C> {
+    fn default() -> Self {
+        TimerWheel::new()
+    }
+}
+
+#[cfg(test)]
+mod test_timer_wheel {
+
+    use std::time::Duration;
+
+    use super::*;
+    use agent::AgentId;
+    use message::{Content, new_id};
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Reminder(u64);
+
+    impl Content for Reminder {}
+
+    fn reminder(n: u64) -> Message<Reminder> {
+        Message::inform((0, AgentId::new(0, 0))).content(Reminder(n)).build()
+    }
+
+    /// Tick at which each reminder fires, the wall clock staying at `now`.
+    fn fire(wheel: &mut TimerWheel<Reminder>, ticks: u64, now: Timestamp) -> Vec<(u64, u64)> {
+        (1..=ticks)
+            .flat_map(|tick| wheel.advance(now).into_iter().map(move |m| (tick, m.content.0)))
+            .collect()
+    }
+
+    #[test]
+    fn it_should_fire_the_timers_at_their_tick() {
+        let mut wheel = TimerWheel::new();
+        for &ticks in &[0, 1, 3, NB_SLOTS as u64, NB_SLOTS as u64 + 2, 3 * NB_SLOTS as u64] {
+            wheel.schedule(new_id(), Delay::Ticks(ticks), reminder(ticks), Timestamp(0));
+        }
+
+        let expected = vec![(1, 0), (1, 1), (3, 3), (64, 64), (66, 66), (192, 192)];
+        assert_eq!(expected, fire(&mut wheel, 200, Timestamp(0)));
+        assert_eq!(0, wheel.len());
+    }
+
+    #[test]
+    fn it_should_fire_the_timers_once_their_deadline_is_passed() {
+        let mut wheel = TimerWheel::new();
+        wheel.schedule(new_id(), Delay::At(Timestamp(1_000)), reminder(0), Timestamp(0));
+        wheel.schedule(new_id(), Delay::After(Duration::from_secs(2)), reminder(1), Timestamp(0));
+
+        assert!(wheel.advance(Timestamp(999)).is_empty());
+        assert_eq!(vec![(1, 0)], fire(&mut wheel, 1, Timestamp(1_000)));
+        assert_eq!(vec![(1, 1)], fire(&mut wheel, 1, Timestamp(5_000)));
+    }
+
+    #[test]
+    fn it_should_cancel_a_timer() {
+        let mut wheel = TimerWheel::new();
+        let tick = new_id();
+        let deadline = new_id();
+        wheel.schedule(tick, Delay::Ticks(2), reminder(0), Timestamp(0));
+        wheel.schedule(deadline, Delay::At(Timestamp(10)), reminder(1), Timestamp(0));
+
+        assert_eq!(Some(Reminder(0)), wheel.cancel(tick).map(|m| m.content));
+        assert_eq!(Some(Reminder(1)), wheel.cancel(deadline).map(|m| m.content));
+        assert!(wheel.cancel(tick).is_none());
+        assert!(fire(&mut wheel, 3, Timestamp(10)).is_empty());
+    }
+
+    #[test]
+    fn it_should_replace_a_timer_scheduled_again() {
+        let mut wheel = TimerWheel::new();
+        let id = new_id();
+        wheel.schedule(id, Delay::Ticks(1), reminder(0), Timestamp(0));
+        wheel.schedule(id, Delay::Ticks(2), reminder(1), Timestamp(0));
+
+        assert_eq!(1, wheel.len());
+        assert_eq!(vec![(2, 1)], fire(&mut wheel, 3, Timestamp(0)));
+    }
+
+    #[test]
+    fn it_should_list_the_ticks_left_of_the_pending_timers() {
+        let mut wheel = TimerWheel::new();
+        wheel.schedule(new_id(), Delay::Ticks(5), reminder(0), Timestamp(0));
+        wheel.schedule(new_id(), Delay::Ticks(100), reminder(1), Timestamp(0));
+        wheel.schedule(new_id(), Delay::At(Timestamp(10)), reminder(2), Timestamp(0));
+        fire(&mut wheel, 2, Timestamp(0));
+
+        let due: Vec<Delay> = wheel.pending().into_iter().map(|timer| timer.due).collect();
+        assert_eq!(vec![Delay::Ticks(3), Delay::Ticks(98), Delay::At(Timestamp(10))], due);
+    }
+}
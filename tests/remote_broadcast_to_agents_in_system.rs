@@ -151,17 +151,17 @@ impl Content for Protocol {}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Subject {
-    id: usize,
+    id: AgentId,
 }
 
 impl Agent for Subject {
     type C = Protocol;
 
-    fn id(&self) -> usize {
+    fn id(&self) -> AgentId {
         self.id
     }
 
-    fn set_id(&mut self, id: usize) {
+    fn set_id(&mut self, id: AgentId) {
         self.id = id
     }
 
@@ -174,33 +174,26 @@ impl Agent for Subject {
     }
 
     fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
-        Some(vec![Message::new(
-            Performative::Inform,
-            Recipient::Broadcast {
-                system_id: Some(OBS_SHOULD_REICV_SYSTEM_ID),
-            },
-            0,
-            1,
-            None,
-            None,
-            None,
-            None,
-            Protocol::Event(MAGIC_EVENT),
-        )])
+        Some(vec![
+            Message::inform(Recipient::Broadcast { system_id: Some(OBS_SHOULD_REICV_SYSTEM_ID) })
+                .priority(1)
+                .content(Protocol::Event(MAGIC_EVENT))
+                .build(),
+        ])
     }
 }
 
 pub struct SubjectFactory;
 
 impl AgentFactory<Subject> for SubjectFactory {
-    fn create(&self, agent_id: usize) -> Subject {
+    fn create(&self, agent_id: AgentId) -> Subject {
         Subject { id: agent_id }
     }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Observer {
-    id: usize,
+    id: AgentId,
     // Set at Option for Deserialize which use Default::default for the field attribute: skip
     #[serde(skip)]
     sender: Option<Sender<()>>,
@@ -210,11 +203,11 @@ pub struct Observer {
 impl Agent for Observer {
     type C = Protocol;
 
-    fn id(&self) -> usize {
+    fn id(&self) -> AgentId {
         self.id
     }
 
-    fn set_id(&mut self, id: usize) {
+    fn set_id(&mut self, id: AgentId) {
         self.id = id
     }
 
@@ -253,7 +246,7 @@ pub struct ObserverFactory {
 }
 
 impl AgentFactory<Observer> for ObserverFactory {
-    fn create(&self, agent_id: usize) -> Observer {
+    fn create(&self, agent_id: AgentId) -> Observer {
         Observer {
             id: agent_id,
             sender: Some(self.sender.clone()),
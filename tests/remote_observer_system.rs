@@ -105,17 +105,17 @@ impl Content for Protocol {}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Subject {
-    id: usize,
+    id: AgentId,
 }
 
 impl Agent for Subject {
     type C = Protocol;
 
-    fn id(&self) -> usize {
+    fn id(&self) -> AgentId {
         self.id
     }
 
-    fn set_id(&mut self, id: usize) {
+    fn set_id(&mut self, id: AgentId) {
         self.id = id
     }
 
@@ -129,20 +129,10 @@ impl Agent for Subject {
 
     fn act(&mut self) -> Option<Vec<Message<Self::C>>> {
         Some(vec![
-            Message::new(
-                Performative::Inform,
-                Recipient::Agent {
-                    agent_id: 0,
-                    system_id: OBSERVER_SYSTEM_ID,
-                },
-                0,
-                1,
-                None,
-                None,
-                None,
-                None,
-                Protocol::Event(MAGIC_EVENT),
-            ),
+            Message::inform((OBSERVER_SYSTEM_ID, AgentId::new(0, 0)))
+                .priority(1)
+                .content(Protocol::Event(MAGIC_EVENT))
+                .build(),
         ])
     }
 }
@@ -150,14 +140,14 @@ impl Agent for Subject {
 pub struct SubjectFactory;
 
 impl AgentFactory<Subject> for SubjectFactory {
-    fn create(&self, agent_id: usize) -> Subject {
+    fn create(&self, agent_id: AgentId) -> Subject {
         Subject { id: agent_id }
     }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Observer {
-    id: usize,
+    id: AgentId,
     // Set at Option for Deserialize which use Default::default for the field attribute: skip
     #[serde(skip)]
     sender: Option<Sender<()>>,
@@ -166,11 +156,11 @@ pub struct Observer {
 impl Agent for Observer {
     type C = Protocol;
 
-    fn id(&self) -> usize {
+    fn id(&self) -> AgentId {
         self.id
     }
 
-    fn set_id(&mut self, id: usize) {
+    fn set_id(&mut self, id: AgentId) {
         self.id = id
     }
 
@@ -205,7 +195,7 @@ pub struct ObserverFactory {
 }
 
 impl AgentFactory<Observer> for ObserverFactory {
-    fn create(&self, agent_id: usize) -> Observer {
+    fn create(&self, agent_id: AgentId) -> Observer {
         Observer {
             id: agent_id,
             sender: Some(self.sender.clone()),